@@ -0,0 +1,768 @@
+use crate::{
+  configuration::{ChangelogSource, ChangelogTransformations},
+  error,
+  file::ToRead,
+  git,
+  publish::Release,
+  template,
+  util::{self, VersionType},
+};
+use chrono::Local;
+use inquire::Editor;
+use std::{collections::HashMap, fs, path::PathBuf};
+
+/// Sections of a generated changelog, in order; commit types not listed here (such as `chore`,
+/// `ci`, `build`, `style` and `test`) are left out
+static SECTIONS: &[(&str, &str)] = &[
+  ("feat", "Features"),
+  ("fix", "Bug Fixes"),
+  ("perf", "Performance"),
+  ("refactor", "Refactoring"),
+  ("docs", "Documentation"),
+  ("revert", "Reverts"),
+];
+
+static OTHER: &str = "Other Changes";
+
+/// Resolve the changelog of the version being published
+pub fn read(
+  configuration: Option<&ChangelogSource>,
+  file: Option<PathBuf>,
+  version: &str,
+  tag: &str,
+) -> Result<Option<String>, error::AxolotlError> {
+  if let Some(file) = file {
+    return Ok(Some(ToRead::new(file)?.read_to_string()?));
+  }
+
+  Ok(match configuration {
+    Some(ChangelogSource::File {
+      file,
+      section: false,
+      ..
+    }) => Some(ToRead::new(file)?.read_to_string()?),
+    Some(ChangelogSource::File {
+      file,
+      section: true,
+      unreleased,
+    }) => {
+      let contents = ToRead::new(file)?.read_to_string()?;
+
+      // the Unreleased section is only renamed once the version has been published (see
+      // `rename_unreleased`)
+      match (section(&contents, version), unreleased) {
+        (Some(section), _) => Some(section),
+        (None, true) => match unreleased_section(&contents) {
+          Some(section) => Some(section),
+          None => return Err(missing_section(file, version)),
+        },
+        (None, false) => return Err(missing_section(file, version)),
+      }
+    }
+    Some(ChangelogSource::Editor) => Some(util::require(
+      None,
+      "changelog",
+      "--changelog-file / AXOLOTL_CHANGELOG_FILE",
+      || Ok(Editor::new("Write the changelog").prompt()?),
+    )?),
+    Some(ChangelogSource::Git { edit }) => {
+      let generated = generate(tag)?;
+
+      if *edit && util::interactive() {
+        Some(
+          Editor::new("Edit the changelog")
+            .with_predefined_text(&generated)
+            .prompt()?,
+        )
+      } else {
+        Some(generated)
+      }
+    }
+    None => None,
+  })
+}
+
+/// Rename the Unreleased section of the changelog file to the version, if that is where the
+/// changelog of the published version was taken from
+pub fn rename_unreleased(
+  configuration: Option<&ChangelogSource>,
+  changelog: Option<&str>,
+  version: &str,
+  dry_run: bool,
+) -> Result<(), error::AxolotlError> {
+  let Some(ChangelogSource::File {
+    file,
+    section: true,
+    unreleased: true,
+  }) = configuration
+  else {
+    return Ok(());
+  };
+
+  let contents = ToRead::new(file)?.read_to_string()?;
+
+  if section(&contents, version).is_some() || unreleased_section(&contents).as_deref() != changelog
+  {
+    return Ok(());
+  }
+
+  if dry_run {
+    info!(
+      "Would rename the Unreleased section of {} to {}",
+      file, version
+    );
+  } else {
+    fs::write(file, release_unreleased(&contents, version))?;
+    info!("Renamed the Unreleased section of {} to {}", file, version);
+  }
+
+  Ok(())
+}
+
+/// Render a template (such as the changelog template) with the variables of the release
+pub fn render(template: &str, release: &Release) -> Result<String, error::AxolotlError> {
+  let mut variables = variables(
+    &release.version,
+    release.version_type,
+    &unique(
+      release
+        .targets
+        .iter()
+        .flat_map(|target| &target.game_versions),
+    ),
+    &unique(release.targets.iter().flat_map(|target| &target.loaders)),
+    release.commit.as_deref(),
+  );
+
+  variables.extend([
+    ("changelog", release.changelog.clone().unwrap_or_default()),
+    ("tag", release.tag.clone()),
+    (
+      "previous_tag",
+      git::previous_tag(&release.tag)
+        .ok()
+        .flatten()
+        .unwrap_or_default(),
+    ),
+  ]);
+
+  template::render(template, &variables)
+}
+
+/// The variables of a release that are known before its tag, which the tag template is rendered
+/// with; `mc` is only set if there is a game version
+pub fn variables(
+  version: &str,
+  version_type: VersionType,
+  game_versions: &[String],
+  loaders: &[String],
+  commit: Option<&str>,
+) -> HashMap<&'static str, String> {
+  let flag = |value: bool| if value { "true" } else { "" }.to_string();
+
+  let mut variables = HashMap::from([
+    ("version", version.to_string()),
+    ("version_type", version_type.to_string().to_lowercase()),
+    ("game_versions", game_versions.join(", ")),
+    ("loaders", loaders.join(", ")),
+    ("date", Local::now().format("%Y-%m-%d").to_string()),
+    (
+      "commit",
+      match commit {
+        Some(commit) => commit.to_string(),
+        None => git::head().unwrap_or_default(),
+      },
+    ),
+    ("release", flag(version_type == VersionType::Release)),
+    ("beta", flag(version_type == VersionType::Beta)),
+    ("alpha", flag(version_type == VersionType::Alpha)),
+    ("prerelease", flag(version_type != VersionType::Release)),
+  ]);
+
+  if let Some(mc) = game_versions.first() {
+    variables.insert("mc", mc.clone());
+  }
+
+  variables
+}
+
+/// Where issues, mentions and comparisons in a changelog link to
+#[derive(Debug)]
+pub struct Links {
+  /// The web address of the forge, which profiles are under
+  pub host: String,
+  /// The web address of the repository
+  pub repository: String,
+  /// What the paths of issues and comparisons in the repository start with, such as `/-` on
+  /// GitLab
+  pub prefix: &'static str,
+}
+
+/// Apply a distributor's transformations to the changelog, linking to the repository of `links`
+pub fn transform(
+  changelog: Option<&str>,
+  transformations: Option<&ChangelogTransformations>,
+  links: Option<&Links>,
+  tag: &str,
+) -> Result<Option<String>, error::AxolotlError> {
+  let (Some(changelog), Some(transformations)) = (changelog, transformations) else {
+    return Ok(changelog.map(str::to_string));
+  };
+
+  let links = || {
+    links.ok_or_else(|| {
+      error::AxolotlError::Error(
+        "Linking issues, mentions or comparisons in the changelog requires a repository"
+          .to_string(),
+      )
+    })
+  };
+
+  let mut changelog = changelog.to_string();
+
+  if transformations.strip_comments {
+    changelog = strip_comments(&changelog);
+  }
+
+  if transformations.link_issues {
+    let links = links()?;
+    changelog = link(
+      &changelog,
+      '#',
+      |c| c.is_ascii_digit(),
+      |issue| {
+        format!(
+          "[#{}]({}{}/issues/{})",
+          issue, links.repository, links.prefix, issue
+        )
+      },
+    );
+  }
+
+  if transformations.link_mentions {
+    let links = links()?;
+    changelog = link(
+      &changelog,
+      '@',
+      |c| c.is_ascii_alphanumeric() || c == '-',
+      |user| format!("[@{}]({}/{})", user, links.host, user),
+    );
+  }
+
+  if transformations.compare_link {
+    let links = links()?;
+
+    if let Some(previous) = git::previous_tag(tag)? {
+      changelog = format!(
+        "{}\n\n**Full changelog**: {}{}/compare/{}...{}",
+        changelog.trim_end(),
+        links.repository,
+        links.prefix,
+        previous,
+        tag
+      );
+    }
+  }
+
+  Ok(Some(changelog))
+}
+
+fn strip_comments(changelog: &str) -> String {
+  let mut stripped = String::new();
+  let mut rest = changelog;
+
+  while let Some(start) = rest.find("<!--") {
+    stripped.push_str(&rest[..start]);
+
+    rest = match rest[start..].find("-->") {
+      Some(end) => &rest[start + end + 3..],
+      None => "",
+    };
+  }
+
+  stripped.push_str(rest);
+  stripped
+}
+
+/// Replace every `{prefix}{name}` at the start of a word (where every character of the name
+/// satisfies the predicate) with a link
+fn link(
+  changelog: &str,
+  prefix: char,
+  valid: impl Fn(char) -> bool,
+  format: impl Fn(&str) -> String,
+) -> String {
+  let mut linked = String::new();
+  let mut previous: Option<char> = None;
+  let mut rest = changelog;
+
+  while let Some(c) = rest.chars().next() {
+    let starts_word = previous.is_none_or(|p| p.is_whitespace() || p == '(');
+    let name = rest[c.len_utf8()..]
+      .split(|c: char| !valid(c))
+      .next()
+      .unwrap_or_default();
+
+    if c == prefix && starts_word && !name.is_empty() {
+      linked.push_str(&format(name));
+      previous = name.chars().last();
+      rest = &rest[c.len_utf8() + name.len()..];
+    } else {
+      linked.push(c);
+      previous = Some(c);
+      rest = &rest[c.len_utf8()..];
+    }
+  }
+
+  linked
+}
+
+/// The values without duplicates, in the order they first appear in
+pub fn unique<'a>(values: impl Iterator<Item = &'a String>) -> Vec<String> {
+  let mut unique: Vec<String> = vec![];
+
+  for value in values {
+    if !unique.contains(value) {
+      unique.push(value.clone());
+    }
+  }
+
+  unique
+}
+
+fn missing_section(file: &str, version: &str) -> error::AxolotlError {
+  error::AxolotlError::Error(format!(
+    "Could not find a section for {} in {}",
+    version, file
+  ))
+}
+
+/// The version named by a Keep a Changelog heading such as `## [1.2.3] - 2024-01-01`, lowercased
+fn heading(line: &str) -> Option<String> {
+  let heading = line.strip_prefix("## ")?.trim();
+  let name = heading.split_whitespace().next()?;
+  let name = name.trim_start_matches('[').trim_end_matches(']');
+
+  Some(name.strip_prefix('v').unwrap_or(name).to_lowercase())
+}
+
+/// The contents of the section for the version (or `unreleased`), without its heading
+fn section(contents: &str, version: &str) -> Option<String> {
+  let version = version.strip_prefix('v').unwrap_or(version).to_lowercase();
+  let mut lines = contents.lines();
+
+  lines.find(|line| heading(line).as_ref() == Some(&version))?;
+
+  Some(
+    lines
+      // the section ends at the next version, or at the link reference definitions at the end
+      .take_while(|line| !line.starts_with("## ") && !is_link_definition(line))
+      .collect::<Vec<_>>()
+      .join("\n")
+      .trim()
+      .to_string(),
+  )
+}
+
+fn is_link_definition(line: &str) -> bool {
+  line.starts_with('[') && line.contains("]: ")
+}
+
+fn unreleased_section(contents: &str) -> Option<String> {
+  section(contents, "unreleased").filter(|section| !section.is_empty())
+}
+
+/// Rename the Unreleased section to the version, leaving a new empty Unreleased section above it
+fn release_unreleased(contents: &str, version: &str) -> String {
+  let date = Local::now().format("%Y-%m-%d");
+
+  contents
+    .lines()
+    .map(|line| {
+      if heading(line).as_deref() == Some("unreleased") {
+        format!("## [Unreleased]\n\n## [{}] - {}", version, date)
+      } else {
+        line.to_string()
+      }
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+    + if contents.ends_with('\n') { "\n" } else { "" }
+}
+
+/// Generate a changelog from the subjects of the commits since the previous tag, grouped by their
+/// conventional commit type
+pub fn generate(tag: &str) -> Result<String, error::AxolotlError> {
+  let previous = git::previous_tag(tag)?;
+  let subjects = git::subjects(previous.as_deref())?;
+
+  Ok(group(subjects))
+}
+
+/// Group commit subjects into a section per conventional commit type, with those that don't follow
+/// it last, leaving out merges
+fn group(subjects: Vec<String>) -> String {
+  let mut sections = SECTIONS
+    .iter()
+    .map(|(_, title)| (*title, vec![]))
+    .chain([(OTHER, vec![])])
+    .collect::<Vec<(&str, Vec<String>)>>();
+
+  for subject in subjects {
+    // git leaves out merge commits, but not ones made without a second parent, such as squashed
+    // merges that kept the merge's subject
+    if subject.starts_with("Merge ") {
+      continue;
+    }
+
+    let (section, entry) = match conventional(&subject) {
+      Some((kind, scope, breaking, description)) => {
+        let Some(index) = SECTIONS.iter().position(|(k, _)| *k == kind) else {
+          continue;
+        };
+
+        let mut entry = String::new();
+
+        if breaking {
+          entry.push_str("**BREAKING** ");
+        }
+
+        if let Some(scope) = scope {
+          entry.push_str(&format!("**{}:** ", scope));
+        }
+
+        entry.push_str(description);
+
+        (index, entry)
+      }
+      None => (SECTIONS.len(), subject),
+    };
+
+    sections[section].1.push(entry);
+  }
+
+  sections
+    .into_iter()
+    .filter(|(_, entries)| !entries.is_empty())
+    .map(|(title, entries)| {
+      format!(
+        "## {}\n\n{}\n",
+        title,
+        entries
+          .iter()
+          .map(|entry| format!("- {}", entry))
+          .collect::<Vec<_>>()
+          .join("\n")
+      )
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// Split a conventional commit subject (`type(scope)!: description`) into its parts
+fn conventional(subject: &str) -> Option<(String, Option<&str>, bool, &str)> {
+  let (header, description) = subject.split_once(": ")?;
+  let (header, breaking) = match header.strip_suffix('!') {
+    Some(header) => (header, true),
+    None => (header, false),
+  };
+  let (kind, scope) = match header.split_once('(') {
+    Some((kind, scope)) => (kind, Some(scope.strip_suffix(')')?)),
+    None => (header, None),
+  };
+
+  if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) {
+    return None;
+  }
+
+  Some((kind.to_lowercase(), scope, breaking, description.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const CHANGELOG: &str = "# Changelog
+
+## [Unreleased]
+
+- Upcoming change
+
+## [1.2.0] - 2024-02-01
+
+### Added
+
+- A feature
+
+## v1.1.0
+
+- A fix
+
+[Unreleased]: https://example.com/compare/v1.2.0...HEAD
+[1.2.0]: https://example.com/compare/v1.1.0...v1.2.0
+";
+
+  #[test]
+  fn heading_names_the_version() {
+    assert_eq!(
+      heading("## [1.2.0] - 2024-02-01"),
+      Some("1.2.0".to_string())
+    );
+    assert_eq!(heading("## v1.1.0"), Some("1.1.0".to_string()));
+    assert_eq!(heading("## [Unreleased]"), Some("unreleased".to_string()));
+    assert_eq!(heading("### Added"), None);
+    assert_eq!(heading("# Changelog"), None);
+  }
+
+  #[test]
+  fn section_ends_at_the_next_heading() {
+    assert_eq!(
+      section(CHANGELOG, "1.2.0"),
+      Some("### Added\n\n- A feature".to_string())
+    );
+    assert_eq!(section(CHANGELOG, "v1.2.0"), section(CHANGELOG, "1.2.0"));
+  }
+
+  #[test]
+  fn section_ends_at_the_link_definitions() {
+    assert_eq!(section(CHANGELOG, "1.1.0"), Some("- A fix".to_string()));
+  }
+
+  #[test]
+  fn section_is_missing_for_unknown_versions() {
+    assert_eq!(section(CHANGELOG, "1.0.0"), None);
+  }
+
+  #[test]
+  fn unreleased_section_is_case_insensitive() {
+    assert_eq!(
+      unreleased_section(CHANGELOG),
+      Some("- Upcoming change".to_string())
+    );
+    assert_eq!(
+      unreleased_section("## [UNRELEASED]\n\n- Change\n"),
+      Some("- Change".to_string())
+    );
+  }
+
+  #[test]
+  fn empty_unreleased_section_is_missing() {
+    assert_eq!(unreleased_section("## [Unreleased]\n\n## [1.0.0]\n"), None);
+  }
+
+  #[test]
+  fn release_unreleased_renames_the_section() {
+    let date = Local::now().format("%Y-%m-%d");
+    let released = release_unreleased(CHANGELOG, "1.3.0");
+
+    assert!(released.contains(&format!(
+      "## [Unreleased]\n\n## [1.3.0] - {}\n\n- Upcoming change\n\n## [1.2.0]",
+      date
+    )));
+    assert_eq!(
+      section(&released, "1.3.0"),
+      Some("- Upcoming change".to_string())
+    );
+    assert_eq!(unreleased_section(&released), None);
+  }
+
+  #[test]
+  fn release_unreleased_keeps_the_trailing_newline() {
+    assert!(release_unreleased(CHANGELOG, "1.3.0").ends_with('\n'));
+    assert!(!release_unreleased("## [Unreleased]\n\n- Change", "1.3.0").ends_with('\n'));
+  }
+
+  #[test]
+  fn conventional_splits_the_subject() {
+    assert_eq!(
+      conventional("feat: add a flag"),
+      Some(("feat".to_string(), None, false, "add a flag"))
+    );
+    assert_eq!(
+      conventional("Fix(maven)!: drop the old layout"),
+      Some((
+        "fix".to_string(),
+        Some("maven"),
+        true,
+        "drop the old layout"
+      ))
+    );
+    assert_eq!(conventional("Update the readme"), None);
+    assert_eq!(conventional("v2: something"), None);
+    assert_eq!(conventional("feat(scope: unclosed"), None);
+  }
+
+  #[test]
+  fn group_sorts_subjects_into_sections() {
+    let subjects = [
+      "fix(github): retry uploads",
+      "feat!: rename the command",
+      "chore: bump dependencies",
+      "Update the readme",
+      "Merge branch 'main' into feature",
+      "feat(modrinth): upload dependencies",
+      "Merge pull request #3 from user/feature",
+    ];
+
+    assert_eq!(
+      group(subjects.iter().map(|subject| subject.to_string()).collect()),
+      "## Features
+
+- **BREAKING** rename the command
+- **modrinth:** upload dependencies
+
+## Bug Fixes
+
+- **github:** retry uploads
+
+## Other Changes
+
+- Update the readme
+"
+    );
+  }
+
+  #[test]
+  fn group_leaves_out_merges() {
+    let grouped = group(vec![
+      "Merge branch 'feature'".to_string(),
+      "Merge pull request #3 from user/feature".to_string(),
+    ]);
+
+    assert!(!grouped.contains("Merge"));
+    assert_eq!(grouped, "");
+  }
+
+  #[test]
+  fn group_without_subjects_is_empty() {
+    assert_eq!(group(vec![]), "");
+  }
+
+  fn links() -> Links {
+    Links {
+      host: "https://github.com".to_string(),
+      repository: "https://github.com/user/repo".to_string(),
+      prefix: "",
+    }
+  }
+
+  #[test]
+  fn transform_links_issues_and_mentions_to_the_repository() {
+    let transformations = ChangelogTransformations {
+      link_issues: true,
+      link_mentions: true,
+      ..Default::default()
+    };
+
+    assert_eq!(
+      transform(
+        Some("- Fix #12 (thanks @some-one), not a#3"),
+        Some(&transformations),
+        Some(&links()),
+        "v1.0.0",
+      )
+      .unwrap(),
+      Some(
+        "- Fix [#12](https://github.com/user/repo/issues/12) (thanks \
+         [@some-one](https://github.com/some-one)), not a#3"
+          .to_string()
+      )
+    );
+  }
+
+  #[test]
+  fn transform_strips_comments() {
+    let transformations = ChangelogTransformations {
+      strip_comments: true,
+      ..Default::default()
+    };
+
+    assert_eq!(
+      transform(
+        Some("- A<!-- hidden --> change<!-- unclosed"),
+        Some(&transformations),
+        None,
+        "v1.0.0",
+      )
+      .unwrap(),
+      Some("- A change".to_string())
+    );
+  }
+
+  #[test]
+  fn transform_links_only_with_a_repository() {
+    let transformations = ChangelogTransformations {
+      link_issues: true,
+      ..Default::default()
+    };
+
+    assert!(transform(Some("- Fix #1"), Some(&transformations), None, "v1.0.0").is_err());
+    assert_eq!(
+      transform(Some("- Fix #1"), None, None, "v1.0.0").unwrap(),
+      Some("- Fix #1".to_string())
+    );
+  }
+
+  #[test]
+  fn transform_links_issues_under_the_prefix() {
+    let transformations = ChangelogTransformations {
+      link_issues: true,
+      ..Default::default()
+    };
+    let links = Links {
+      host: "https://gitlab.com".to_string(),
+      repository: "https://gitlab.com/group/project".to_string(),
+      prefix: "/-",
+    };
+
+    assert_eq!(
+      transform(
+        Some("- Fix #3"),
+        Some(&transformations),
+        Some(&links),
+        "v1.0.0"
+      )
+      .unwrap(),
+      Some("- Fix [#3](https://gitlab.com/group/project/-/issues/3)".to_string())
+    );
+  }
+
+  #[test]
+  fn variables_set_mc_to_the_first_game_version() {
+    let variables = variables(
+      "1.0.0",
+      VersionType::Beta,
+      &["1.21".to_string(), "1.21.1".to_string()],
+      &["fabric".to_string()],
+      Some("abc"),
+    );
+
+    assert_eq!(
+      template::render("mc{{mc}}-{{version}}", &variables).unwrap(),
+      "mc1.21-1.0.0"
+    );
+    assert_eq!(
+      template::render(
+        "{{game_versions}} {{loaders}} {{commit}} {{#if prerelease}}{{version_type}}{{/if}}",
+        &variables
+      )
+      .unwrap(),
+      "1.21, 1.21.1 fabric abc beta"
+    );
+  }
+
+  #[test]
+  fn variables_leave_out_mc_without_game_versions() {
+    let variables = variables("1.0.0", VersionType::Release, &[], &[], Some("abc"));
+
+    assert!(template::render("mc{{mc}}-{{version}}", &variables).is_err());
+    assert_eq!(variables["game_versions"], "");
+  }
+
+  #[test]
+  fn unique_keeps_the_first_of_each_value() {
+    let values = ["b", "a", "b"].map(str::to_string);
+
+    assert_eq!(unique(values.iter()), ["b", "a"]);
+  }
+}
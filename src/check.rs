@@ -0,0 +1,177 @@
+use crate::{
+  configuration::Configuration, constants, error, file::ToRead, modrinth, network, util,
+  CheckArguments,
+};
+use inquire::Confirm;
+use modrinth_api::{apis, models::Project};
+use std::{cmp::Ordering, collections::HashMap, fs};
+use toml_edit::{value, DocumentMut, Item, TableLike, Value};
+
+/// Resolve the Modrinth projects referenced by slug (or by an outdated id) in the configuration
+/// file, and offer to rewrite it with their canonical ids
+pub async fn check(arguments: CheckArguments) -> Result<(), error::AxolotlError> {
+  // make sure the configuration is valid before touching it
+  let configuration = Configuration::read()?;
+  network::configure(configuration.network.as_ref());
+
+  let contents = ToRead::new(constants::CONFIGURATION)?.read_to_string()?;
+  let mut document = contents.parse::<DocumentMut>()?;
+
+  let (keys, _) = util::get_keys()?;
+  let config = network::modrinth(keys.with_environment().token(modrinth::REGISTERED.name))?;
+
+  let mut projects = HashMap::new();
+
+  if let Some(modrinth) = document.get_mut("modrinth").and_then(Item::as_table_mut) {
+    if resolve(&config, &mut projects, "[modrinth]", modrinth, true).await? {
+      modrinth.sort_values_by(|a, _, b, _| rank(a, b));
+    }
+
+    if let Some(dependencies) = modrinth
+      .get_mut("dependencies")
+      .and_then(Item::as_array_of_tables_mut)
+    {
+      for (index, dependency) in dependencies.iter_mut().enumerate() {
+        let location = format!("[modrinth] dependency {}", index + 1);
+
+        if resolve(&config, &mut projects, &location, dependency, true).await? {
+          dependency.sort_values_by(|a, _, b, _| rank(a, b));
+        }
+      }
+    }
+  }
+
+  for name in configuration.targets.keys() {
+    let Some(dependencies) = document
+      .get_mut("targets")
+      .and_then(|targets| targets.get_mut(name))
+      .and_then(|target| target.get_mut("dependencies"))
+    else {
+      continue;
+    };
+
+    match dependencies {
+      Item::ArrayOfTables(dependencies) => {
+        for (index, dependency) in dependencies.iter_mut().enumerate() {
+          let location = format!("[targets.{}] dependency {}", name, index + 1);
+
+          if resolve(&config, &mut projects, &location, dependency, true).await? {
+            dependency.sort_values_by(|a, _, b, _| rank(a, b));
+          }
+        }
+      }
+      Item::Value(Value::Array(dependencies)) => {
+        for (index, dependency) in dependencies.iter_mut().enumerate() {
+          if let Value::InlineTable(dependency) = dependency {
+            let location = format!("[targets.{}] dependency {}", name, index + 1);
+
+            if resolve(&config, &mut projects, &location, dependency, false).await? {
+              dependency.sort_values_by(|a, _, b, _| rank(a, b));
+            }
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+
+  let rewritten = document.to_string();
+
+  if rewritten == contents {
+    info!("{} is up to date", constants::CONFIGURATION);
+    return Ok(());
+  }
+
+  if arguments.yes
+    || (util::interactive()
+      && Confirm::new(&format!(
+        "Rewrite {} with the resolved ids?",
+        constants::CONFIGURATION
+      ))
+      .with_default(true)
+      .prompt()?)
+  {
+    fs::write(constants::CONFIGURATION, rewritten)?;
+    info!("{} has been rewritten", constants::CONFIGURATION);
+  } else {
+    info!(
+      "Run with --yes to rewrite {} with the resolved ids",
+      constants::CONFIGURATION
+    );
+  }
+
+  Ok(())
+}
+
+/// Replace the `id` or `slug` of the table with the canonical id of the project, explaining it in a
+/// comment if the table can hold one (inline tables can't), and return whether a slug was replaced
+///
+/// Canonical ids are left as they are, and a `slug` next to an `id` is dropped, as a dependency
+/// can't have both
+async fn resolve(
+  configuration: &apis::configuration::Configuration,
+  projects: &mut HashMap<String, Project>,
+  location: &str,
+  table: &mut dyn TableLike,
+  comment: bool,
+) -> Result<bool, error::AxolotlError> {
+  let key = if table.contains_key("id") {
+    "id"
+  } else {
+    "slug"
+  };
+
+  let Some(reference) = table.get(key).and_then(Item::as_str).map(str::to_string) else {
+    return Ok(false);
+  };
+
+  if key == "id" && table.contains_key("slug") {
+    info!(
+      "{}: dropping the slug, as the id {} is used",
+      location, reference
+    );
+    table.remove("slug");
+  }
+
+  if !projects.contains_key(&reference) {
+    let project = network::retry(&format!("Looking up {}", reference), || async {
+      Ok(apis::projects_api::get_project(configuration, &reference).await?)
+    })
+    .await?;
+    projects.insert(reference.clone(), project);
+  }
+
+  let project = &projects[&reference];
+
+  if key == "id" && reference == project.id {
+    return Ok(false);
+  }
+
+  info!(
+    "{}: {} resolves to {} ({})",
+    location, reference, project.id, project.title
+  );
+
+  // replacing the item rather than the entry keeps the comments above the key
+  match table.get_mut("id") {
+    Some(id) => *id = value(project.id.clone()),
+    None => {
+      table.remove("slug");
+      table.insert("id", value(project.id.clone()));
+    }
+  }
+
+  if comment {
+    if let Some(id) = table.get_mut("id").and_then(Item::as_value_mut) {
+      id.decor_mut()
+        .set_suffix(format!(" # {} ({})", project.title, project.slug));
+    }
+  }
+
+  Ok(key == "slug")
+}
+
+/// Move an `id` that replaced a `slug` to the front, where the slug usually is
+fn rank(a: &toml_edit::Key, b: &toml_edit::Key) -> Ordering {
+  (a.get() != "id").cmp(&(b.get() != "id"))
+}
@@ -1,55 +1,251 @@
 use crate::{constants, error, file::ToRead};
-use modrinth_api::models::{version_dependency::DependencyType, VersionDependency};
+use clap::ValueEnum;
+use modrinth_api::models::{
+  creatable_version::RequestedStatus, version_dependency::DependencyType,
+};
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use std::{collections::BTreeMap, fs, path::PathBuf};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Configuration {
-  pub artifact: Artifact,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub artifact: Option<Artifact>,
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub targets: BTreeMap<String, Target>,
+  /// A template for the tag of every release, `v{{version}}` by default
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub tag: Option<String>,
   pub changelog: Option<Changelog>,
   pub modrinth: Option<Modrinth>,
   pub github: Option<GitHub>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub forgejo: Option<Forgejo>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub gitlab: Option<GitLab>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub maven: Option<Maven>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub hooks: Option<Hooks>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub git: Option<Git>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub network: Option<Network>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Artifact {
   pub folder: PathBuf,
   pub pattern: String,
-  pub game_versions: Vec<String>,
-  pub loaders: Vec<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub game_versions: Option<Vec<String>>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub loaders: Option<Vec<String>>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub additional: Vec<String>,
+  /// A pattern for the sources jar, which is only uploaded to Maven
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub sources: Option<String>,
+}
+
+/// A named artifact, for projects building a separate jar per loader
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Target {
+  #[serde(flatten)]
+  pub artifact: Artifact,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub dependencies: Vec<ModrinthDependency>,
 }
 
-// TODO: drafts
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Modrinth {
   pub id: String,
   #[serde(default = "default_featured")]
   pub featured: bool,
+  #[serde(default)]
+  pub status: ModrinthStatus,
+  /// The status drafts and scheduled versions move to once released
+  #[serde(default)]
+  pub requested_status: RequestedStatus,
+  /// An RFC 3339 timestamp, required if the status is `scheduled`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub release_at: Option<String>,
+  #[serde(default)]
   pub dependencies: Vec<ModrinthDependency>,
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+#[clap(rename_all = "lower")]
+pub enum ModrinthStatus {
+  #[default]
+  Listed,
+  Unlisted,
+  Archived,
+  /// Hidden until released with `axolotl mod release`
+  Draft,
+  /// Released automatically at `release_at`
+  Scheduled,
+}
+
+/// A dependency on a Modrinth project (by `id` or `slug`), optionally pinned to a version by
+/// `version_id` or resolved from a `version` requirement, or on a file outside Modrinth
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModrinthDependency {
-  id: String,
-  dependency_type: DependencyType,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub id: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub slug: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub version_id: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub version: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub file_name: Option<String>,
+  pub dependency_type: DependencyType,
 }
 
-impl Into<VersionDependency> for ModrinthDependency {
-  fn into(self) -> VersionDependency {
-    VersionDependency {
-      version_id: None,
-      project_id: Some(Some(self.id)),
-      file_name: None,
-      dependency_type: self.dependency_type,
-    }
-  }
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitHub {
+  pub repo: (String, String),
+  #[serde(default = "default_draft")]
+  pub draft: bool,
+  /// A template for the name of the release, the tag by default
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub name: Option<String>,
+  /// The branch or commit the tag is created from, if it doesn't exist yet
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub target_commitish: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub make_latest: Option<MakeLatest>,
+  /// Append GitHub's generated release notes to the changelog
+  #[serde(default)]
+  pub generate_release_notes: bool,
+  /// Create a discussion for the release in this category
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub discussion_category_name: Option<String>,
+}
+
+/// Whether a GitHub release becomes the latest release, where `legacy` picks the latest by
+/// creation date and semantic version
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MakeLatest {
+  True,
+  False,
+  Legacy,
 }
 
+/// A repository on a Forgejo or Gitea instance
 #[derive(Debug, Serialize, Deserialize)]
-pub struct GitHub {
+pub struct Forgejo {
+  /// The URL of the instance, such as `https://codeberg.org`
+  pub url: String,
   pub repo: (String, String),
   #[serde(default = "default_draft")]
   pub draft: bool,
+  /// Whether the release is marked as a prerelease, which beta and alpha versions are by default
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub prerelease: Option<bool>,
+}
+
+/// A project on GitLab or a self-hosted instance, whose generic package registry the artifacts are
+/// uploaded to
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GitLab {
+  #[serde(default = "default_gitlab_url")]
+  pub url: String,
+  /// The path (`group/project`) or the id of the project
+  pub project: String,
+  /// The name of the generic package, the path of the project by default
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub package: Option<String>,
+  /// The branch or commit the tag is created from, if it doesn't exist yet
+  #[serde(rename = "ref", default, skip_serializing_if = "Option::is_none")]
+  pub reference: Option<String>,
+}
+
+fn default_gitlab_url() -> String {
+  "https://gitlab.com".to_string()
+}
+
+/// A Maven repository the artifacts are deployed to, over HTTP or to a `file://` folder
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Maven {
+  pub url: String,
+  pub group_id: String,
+  /// Suffixed with `-<name>` for named targets
+  pub artifact_id: String,
+  /// The user the key is sent as the password of, or the key is sent as a bearer token if absent
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub username: Option<String>,
+}
+
+/// Shell commands run before the artifact is located and after every distributor is done
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Hooks {
+  #[serde(default)]
+  pub pre_publish: Vec<String>,
+  #[serde(default)]
+  pub post_publish: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Git {
+  #[serde(default = "default_require_clean")]
+  pub require_clean: bool,
+  #[serde(default = "default_tag")]
+  pub tag: bool,
+  #[serde(default)]
+  pub push: bool,
+  #[serde(default = "default_remote")]
+  pub remote: String,
+}
+
+fn default_require_clean() -> bool {
+  true
+}
+
+fn default_tag() -> bool {
+  true
+}
+
+fn default_remote() -> String {
+  "origin".to_string()
+}
+
+/// How requests to the distributors are retried and timed out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Network {
+  #[serde(default = "default_retries")]
+  pub retries: u32,
+  /// In seconds, without any progress
+  #[serde(default = "default_timeout")]
+  pub timeout: u64,
+  /// In seconds
+  #[serde(default = "default_connect_timeout")]
+  pub connect_timeout: u64,
+}
+
+impl Default for Network {
+  fn default() -> Self {
+    Self {
+      retries: default_retries(),
+      timeout: default_timeout(),
+      connect_timeout: default_connect_timeout(),
+    }
+  }
+}
+
+fn default_retries() -> u32 {
+  5
+}
+
+fn default_timeout() -> u64 {
+  60
+}
+
+fn default_connect_timeout() -> u64 {
+  10
 }
 
 fn default_draft() -> bool {
@@ -63,39 +259,102 @@ fn default_featured() -> bool {
 impl Default for Configuration {
   fn default() -> Self {
     Self {
-      artifact: Artifact {
+      artifact: Some(Artifact {
         folder: PathBuf::from("build/libs"),
         pattern: "mod-#.jar".to_string(),
-        game_versions: vec!["1.xx".to_string()],
-        loaders: vec![
-          "fabric".to_string(),
-          "quilt".to_string(),
-          "forge".to_string(),
-          "neoforge".to_string(),
-        ],
-      },
-      changelog: Some(Changelog::Editor),
+        game_versions: None,
+        loaders: None,
+        additional: vec![],
+        sources: None,
+      }),
+      targets: BTreeMap::new(),
+      tag: None,
+      changelog: Some(Changelog {
+        source: ChangelogSource::Editor,
+        template: None,
+        github: None,
+        modrinth: None,
+        forgejo: None,
+        gitlab: None,
+      }),
       modrinth: Some(Modrinth {
         id: "modrinth project id".to_string(),
         featured: true,
+        status: ModrinthStatus::Listed,
+        requested_status: RequestedStatus::Listed,
+        release_at: None,
         dependencies: vec![ModrinthDependency {
-          id: "P7dR8mSH".to_string(),
+          id: Some("P7dR8mSH".to_string()),
+          slug: None,
+          version_id: None,
+          version: None,
+          file_name: None,
           dependency_type: DependencyType::Required,
         }],
       }),
       github: Some(GitHub {
         repo: ("user".to_string(), "repo".to_string()),
         draft: true,
+        name: None,
+        target_commitish: None,
+        make_latest: None,
+        generate_release_notes: false,
+        discussion_category_name: None,
       }),
+      forgejo: None,
+      gitlab: None,
+      maven: None,
+      hooks: None,
+      git: None,
+      network: None,
     }
   }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Changelog {
+  #[serde(flatten)]
+  pub source: ChangelogSource,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub template: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub github: Option<ChangelogTransformations>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub modrinth: Option<ChangelogTransformations>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub forgejo: Option<ChangelogTransformations>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub gitlab: Option<ChangelogTransformations>,
+}
+
+/// Changes made to the changelog for a single distributor
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ChangelogTransformations {
+  #[serde(default)]
+  pub link_issues: bool,
+  #[serde(default)]
+  pub link_mentions: bool,
+  #[serde(default)]
+  pub strip_comments: bool,
+  #[serde(default)]
+  pub compare_link: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
-pub enum Changelog {
-  File { file: String },
+pub enum ChangelogSource {
+  File {
+    file: String,
+    #[serde(default)]
+    section: bool,
+    #[serde(default)]
+    unreleased: bool,
+  },
   Editor,
+  Git {
+    #[serde(default)]
+    edit: bool,
+  },
 }
 
 impl Configuration {
@@ -108,6 +367,23 @@ impl Configuration {
     Ok(toml::from_str(&file.read_to_string()?)?)
   }
 
+  /// The unnamed `[artifact]` (if any) followed by the named targets, along with their extra
+  /// Modrinth dependencies
+  pub fn targets(&self) -> Vec<(Option<&str>, &Artifact, &[ModrinthDependency])> {
+    self
+      .artifact
+      .iter()
+      .map(|artifact| (None, artifact, &[] as &[_]))
+      .chain(self.targets.iter().map(|(name, target)| {
+        (
+          Some(name.as_str()),
+          &target.artifact,
+          target.dependencies.as_slice(),
+        )
+      }))
+      .collect()
+  }
+
   pub fn write(configuration: Self) -> Result<(), error::AxolotlError> {
     Ok(fs::write(
       constants::CONFIGURATION,
@@ -4,6 +4,9 @@ use std::path::PathBuf;
 
 pub static CONFIGURATION: &str = "axolotl.toml";
 pub static VERSION_REPLACE: &str = "#";
+pub static STATE_FOLDER: &str = ".axolotl";
+pub static STATE: &str = ".axolotl/publish-state.json";
+pub static HISTORY: &str = ".axolotl/history.json";
 
 lazy_static! {
   pub static ref GLOBAL: PathBuf = config_dir().unwrap().join("axolotl");
@@ -0,0 +1,190 @@
+use crate::{
+  changelog::{self, Links},
+  configuration::{ChangelogTransformations, Configuration},
+  error, forgejo, github, gitlab,
+  journal::Shared,
+  keys::Keys,
+  maven, modrinth,
+  publish::Release,
+  PublishArguments,
+};
+use async_trait::async_trait;
+use clap::builder::{PossibleValue, PossibleValuesParser, TypedValueParser};
+use std::fmt;
+
+/// A place releases are published to
+///
+/// Adding a distributor takes a module implementing this, a section for it in the configuration
+/// and an entry in `REGISTRY`
+#[async_trait]
+pub trait Distributor: Send + Sync {
+  fn registered(&self) -> &'static Registered;
+
+  /// Check the configuration and arguments before anything is asked for or created
+  fn validate(&mut self, _arguments: &PublishArguments) -> Result<(), error::AxolotlError> {
+    Ok(())
+  }
+
+  /// Look up whatever publishing needs before the tag is created, so that mistakes are found
+  /// before anything is published anywhere
+  async fn prepare(&mut self, _context: &Context<'_, '_>) -> Result<(), error::AxolotlError> {
+    Ok(())
+  }
+
+  /// Publish the release, recording every step in the journal, and return the URLs it can be
+  /// found at
+  async fn publish(&self, context: &Context<'_, '_>) -> Result<Vec<String>, error::AxolotlError>;
+
+  /// Delete something `publish` created (see `Created::Distributed`), along with its tag if the
+  /// distributor created that too
+  async fn rollback(
+    &self,
+    kind: &str,
+    id: &str,
+    tag: Option<&str>,
+  ) -> Result<(), error::AxolotlError>;
+
+  /// The variables post-publish hooks receive once the release has been published
+  fn environment(&self, urls: &[String]) -> Vec<(String, String)> {
+    if urls.is_empty() {
+      return vec![];
+    }
+
+    vec![(
+      format!("AXOLOTL_{}_URLS", self.registered().name.to_uppercase()),
+      urls.join(" "),
+    )]
+  }
+}
+
+/// A distributor that can be configured
+pub struct Registered {
+  /// The name of its key and of its section in the configuration
+  pub name: &'static str,
+  pub alias: &'static str,
+  /// The name shown in messages
+  pub title: &'static str,
+  /// The distributor, if it is configured and its key was provided or it's for a dry run
+  pub create: for<'a> fn(&'a Configuration, &Keys, bool) -> Option<Box<dyn Distributor + 'a>>,
+}
+
+impl fmt::Debug for Registered {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.name)
+  }
+}
+
+/// Every distributor, in the order they are listed in
+pub static REGISTRY: &[&Registered] = &[
+  &github::REGISTERED,
+  &forgejo::REGISTERED,
+  &gitlab::REGISTERED,
+  &maven::REGISTERED,
+  &modrinth::REGISTERED,
+];
+
+/// What every distributor publishes from
+pub struct Context<'a, 'j> {
+  pub release: &'a Release,
+  pub journal: &'a Shared<'j>,
+  pub force: bool,
+  pub dry_run: bool,
+}
+
+impl Context<'_, '_> {
+  /// The changelog with a distributor's transformations applied, linking to the repository of
+  /// `links`
+  pub fn changelog(
+    &self,
+    transformations: Option<&ChangelogTransformations>,
+    links: Option<&Links>,
+  ) -> Result<Option<String>, error::AxolotlError> {
+    changelog::transform(
+      self.release.changelog.as_deref(),
+      transformations,
+      links,
+      &self.release.tag,
+    )
+  }
+}
+
+/// The distributors that are configured and have a key, in the order of the registry
+///
+/// A dry run sends nothing that needs a key, so it has every configured distributor
+pub fn configured<'a>(
+  configuration: &'a Configuration,
+  keys: &Keys,
+  dry_run: bool,
+) -> Vec<Box<dyn Distributor + 'a>> {
+  REGISTRY
+    .iter()
+    .filter_map(|registered| (registered.create)(configuration, keys, dry_run))
+    .collect()
+}
+
+/// Where the changelogs of distributors without a repository of their own link to, the repository
+/// on the first configured forge
+pub fn links(configuration: &Configuration) -> Option<Links> {
+  configuration
+    .github
+    .as_ref()
+    .map(github::links)
+    .or_else(|| configuration.forgejo.as_ref().map(forgejo::links))
+    .or_else(|| configuration.gitlab.as_ref().and_then(gitlab::links))
+}
+
+/// The key of a configured distributor, or `None` if it is skipped for not having one, which is
+/// reported
+///
+/// A dry run goes ahead without the key, only warning that publishing would skip the distributor
+pub fn token(keys: &Keys, registered: &Registered, dry_run: bool) -> Option<Option<String>> {
+  let token = keys.token(registered.name);
+
+  match token {
+    Some(token) => Some(Some(token)),
+    None if dry_run => {
+      warn!(
+        "A {} token was not provided, publishing would skip distributing to {}",
+        registered.title, registered.title
+      );
+
+      Some(None)
+    }
+    None => {
+      error!(
+        "A {} token was not provided, skipping distributing to {}",
+        registered.title, registered.title
+      );
+
+      None
+    }
+  }
+}
+
+/// The key of a distributor for what only publishing does, which it was created with unless it is
+/// for a dry run
+pub fn key<'t>(
+  token: &'t Option<String>,
+  registered: &Registered,
+) -> Result<&'t str, error::AxolotlError> {
+  token.as_deref().ok_or_else(|| {
+    error::AxolotlError::Error(format!("A {} token was not provided", registered.title))
+  })
+}
+
+pub fn find(name: &str) -> Option<&'static Registered> {
+  REGISTRY
+    .iter()
+    .copied()
+    .find(|registered| registered.name == name || registered.alias == name)
+}
+
+/// Parses the name or alias of a distributor in the registry
+pub fn parser() -> impl TypedValueParser<Value = &'static Registered> {
+  PossibleValuesParser::new(REGISTRY.iter().map(|registered| {
+    PossibleValue::new(registered.name)
+      .alias(registered.alias)
+      .help(format!("[aliases: {}]", registered.alias))
+  }))
+  .map(|name| find(&name).expect("only registered names are parsed"))
+}
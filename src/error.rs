@@ -1,5 +1,11 @@
 use modrinth_api::apis::{
-  projects_api::CheckProjectValidityError, versions_api::CreateVersionError,
+  projects_api::{CheckProjectValidityError, GetProjectError},
+  tags_api::VersionListError,
+  version_files_api::DeleteFileFromHashError,
+  versions_api::{
+    AddFilesToVersionError, CreateVersionError, DeleteVersionError, GetProjectVersionsError,
+    ModifyVersionError, ScheduleVersionError,
+  },
 };
 use thiserror::Error;
 
@@ -20,6 +26,9 @@ pub enum AxolotlError {
   #[error("could not deserialize toml: {0:#?}")]
   TomlDeserializationError(#[from] toml::de::Error),
 
+  #[error("could not parse toml: {0:#?}")]
+  TomlEditError(#[from] toml_edit::TomlError),
+
   #[error("encryption failed: {0:#?}")]
   EncryptionError(#[from] age::EncryptError),
 
@@ -32,24 +41,88 @@ pub enum AxolotlError {
   #[error("failed to decode base64: {0:#?}")]
   Base64DecodeError(#[from] base64::DecodeError),
 
+  /// Boxed, as octocrab's errors would make every result several times larger
   #[error("github api request failed: {0:#?}")]
-  GithubError(#[from] octocrab::Error),
+  GithubError(Box<octocrab::Error>),
+
+  #[error("github asset upload failed with {status}: {content}")]
+  GitHubUploadError {
+    status: reqwest::StatusCode,
+    content: String,
+  },
+
+  #[error("forgejo api request failed with {status}: {content}")]
+  ForgejoError {
+    status: reqwest::StatusCode,
+    content: String,
+  },
+
+  #[error("gitlab api request failed with {status}: {content}")]
+  GitLabError {
+    status: reqwest::StatusCode,
+    content: String,
+  },
+
+  #[error("maven repository request failed with {status}: {content}")]
+  MavenError {
+    status: reqwest::StatusCode,
+    content: String,
+  },
 
   #[error("modrinth project validity check failed: {0:#?}")]
   ModrinthProjectValidityError(#[from] modrinth_api::apis::Error<CheckProjectValidityError>),
 
+  #[error("modrinth project request failed: {0:#?}")]
+  ModrinthProjectError(#[from] modrinth_api::apis::Error<GetProjectError>),
+
   #[error("modrinth version creation failed: {0:#?}")]
   ModrinthCreateVersionError(#[from] modrinth_api::apis::Error<CreateVersionError>),
 
+  #[error("modrinth file upload failed: {0:#?}")]
+  ModrinthAddFilesError(#[from] modrinth_api::apis::Error<AddFilesToVersionError>),
+
+  #[error("modrinth file deletion failed: {0:#?}")]
+  ModrinthDeleteFileError(#[from] modrinth_api::apis::Error<DeleteFileFromHashError>),
+
+  #[error("modrinth version deletion failed: {0:#?}")]
+  ModrinthDeleteVersionError(#[from] modrinth_api::apis::Error<DeleteVersionError>),
+
+  #[error("modrinth version scheduling failed: {0:#?}")]
+  ModrinthScheduleVersionError(#[from] modrinth_api::apis::Error<ScheduleVersionError>),
+
+  #[error("modrinth project version list request failed: {0:#?}")]
+  ModrinthProjectVersionsError(#[from] modrinth_api::apis::Error<GetProjectVersionsError>),
+
+  #[error("modrinth version modification failed: {0:#?}")]
+  ModrinthModifyVersionError(#[from] modrinth_api::apis::Error<ModifyVersionError>),
+
+  #[error("modrinth game version list request failed: {0:#?}")]
+  ModrinthVersionListError(#[from] modrinth_api::apis::Error<VersionListError>),
+
   #[error("modrinth api request failed: {0:#?}")]
   ModrinthError(#[from] modrinth_api::apis::Error<()>),
 
   #[error("invalid header value: {0:#?}")]
   InvalidHeaderValueError(#[from] reqwest::header::InvalidHeaderValue),
 
+  #[error("could not read zip archive: {0:#?}")]
+  ZipError(#[from] zip::result::ZipError),
+
   #[error("reqwest error: {0:#?}")]
   ReqwestError(#[from] reqwest::Error),
 
+  #[error("{source}")]
+  RateLimited {
+    source: Box<AxolotlError>,
+    wait: std::time::Duration,
+  },
+
   #[error("{0:#?}")]
   Error(String),
 }
+
+impl From<octocrab::Error> for AxolotlError {
+  fn from(error: octocrab::Error) -> Self {
+    AxolotlError::GithubError(Box::new(error))
+  }
+}
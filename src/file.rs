@@ -1,7 +1,10 @@
 use tokio::fs::File;
 
-use crate::error;
-use std::{fs, io, path::Path};
+use crate::{error, pattern};
+use std::{
+  fs, io,
+  path::{Path, PathBuf},
+};
 
 pub struct ToRead<P: AsRef<Path>>(P);
 
@@ -17,15 +20,100 @@ impl<P: AsRef<Path> + Clone> ToRead<P> {
     }
   }
 
-  pub fn read_to_string(&self) -> io::Result<String> {
-    fs::read_to_string(self.0.clone())
+  pub fn path(&self) -> &Path {
+    self.0.as_ref()
   }
 
-  pub fn read(&self) -> io::Result<Vec<u8>> {
-    fs::read(self.0.clone())
+  pub fn read_to_string(&self) -> io::Result<String> {
+    fs::read_to_string(self.0.clone())
   }
 
   pub async fn open(&self) -> io::Result<File> {
     File::open(self.0.clone()).await
   }
 }
+
+impl ToRead<PathBuf> {
+  /// Find the single file in the folder whose name matches the glob pattern
+  pub fn find(folder: &Path, pattern: &str) -> Result<Self, error::AxolotlError> {
+    // a missing file falls through, so that the error lists what is in the folder instead
+    if !pattern::is_glob(pattern) && folder.join(pattern).exists() {
+      return Ok(Self(folder.join(pattern)));
+    }
+
+    let candidates = files(folder)?;
+    let matching = candidates
+      .iter()
+      .filter(|file| matches(file, pattern))
+      .collect::<Vec<_>>();
+
+    match matching.as_slice() {
+      [file] => Ok(Self((*file).clone())),
+      [] => Err(error::AxolotlError::Error(format!(
+        "Could not find a file matching {} in {} (considered: {})",
+        pattern,
+        folder.display(),
+        list(candidates.iter())
+      ))),
+      _ => Err(error::AxolotlError::Error(format!(
+        "Found multiple files matching {} in {}: {}",
+        pattern,
+        folder.display(),
+        list(matching.into_iter())
+      ))),
+    }
+  }
+}
+
+/// The files in the folder whose names match the glob pattern
+pub fn glob(folder: &Path, pattern: &str) -> Result<Vec<PathBuf>, error::AxolotlError> {
+  Ok(
+    files(folder)?
+      .into_iter()
+      .filter(|file| matches(file, pattern))
+      .collect(),
+  )
+}
+
+fn files(folder: &Path) -> Result<Vec<PathBuf>, error::AxolotlError> {
+  if !folder.is_dir() {
+    return Err(error::AxolotlError::Error(format!(
+      "Could not find the folder {}",
+      folder.display()
+    )));
+  }
+
+  let mut files = vec![];
+
+  for entry in fs::read_dir(folder)? {
+    let path = entry?.path();
+
+    if path.is_file() {
+      files.push(path);
+    }
+  }
+
+  files.sort();
+
+  Ok(files)
+}
+
+fn matches(file: &Path, pattern: &str) -> bool {
+  file
+    .file_name()
+    .and_then(|name| name.to_str())
+    .is_some_and(|name| pattern::matches(pattern, name))
+}
+
+fn list<'a>(files: impl Iterator<Item = &'a PathBuf>) -> String {
+  let names = files
+    .filter_map(|file| file.file_name())
+    .map(|name| name.to_string_lossy().into_owned())
+    .collect::<Vec<_>>();
+
+  if names.is_empty() {
+    "nothing".to_string()
+  } else {
+    names.join(", ")
+  }
+}
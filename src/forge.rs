@@ -0,0 +1,242 @@
+use crate::{
+  distributor::{Context, Registered},
+  error,
+  journal::Step,
+  network, progress,
+  publish::Asset,
+  rollback::Created,
+};
+use async_trait::async_trait;
+use indicatif::ProgressBar;
+
+/// The releases of a repository on a code forge such as GitHub or Forgejo, which publish the same
+/// way: a release for the tag, with every artifact attached to it
+///
+/// Every method is a single request, retried by `publish` and `rollback`
+#[async_trait]
+pub trait Forge: Sync {
+  type Release: Send + Sync;
+
+  fn registered(&self) -> &'static Registered;
+
+  fn id(release: &Self::Release) -> u64;
+  fn tag(release: &Self::Release) -> &str;
+  fn url(release: &Self::Release) -> &str;
+  /// The ids and names of the files attached to the release
+  fn assets(release: &Self::Release) -> Vec<(u64, &str)>;
+
+  /// The published release for the tag, if there is one
+  async fn get_by_tag(&self, tag: &str) -> Result<Option<Self::Release>, error::AxolotlError>;
+  /// A page of the releases, starting at 1, and whether it is the last one
+  async fn list(&self, page: u32) -> Result<(Vec<Self::Release>, bool), error::AxolotlError>;
+  async fn get(&self, id: u64) -> Result<Self::Release, error::AxolotlError>;
+  async fn create(&self) -> Result<Self::Release, error::AxolotlError>;
+  async fn update(&self, id: u64) -> Result<Self::Release, error::AxolotlError>;
+  async fn delete(&self, id: u64) -> Result<(), error::AxolotlError>;
+
+  async fn tag_exists(&self, tag: &str) -> Result<bool, error::AxolotlError>;
+  async fn delete_tag(&self, tag: &str) -> Result<(), error::AxolotlError>;
+
+  /// Stream an asset to the release, returning its id and where it can be downloaded
+  async fn upload(
+    &self,
+    release: &Self::Release,
+    asset: &Asset,
+    progress: &ProgressBar,
+  ) -> Result<(u64, String), error::AxolotlError>;
+  async fn delete_asset(&self, release: &Self::Release, id: u64)
+    -> Result<(), error::AxolotlError>;
+}
+
+/// Create the release for the tag, or continue with the one the journal or the forge already has,
+/// and upload every asset to it
+pub async fn publish<F: Forge>(
+  forge: &F,
+  context: &Context<'_, '_>,
+  draft: bool,
+) -> Result<Vec<String>, error::AxolotlError> {
+  let Context {
+    release,
+    journal,
+    force,
+    ..
+  } = *context;
+  let registered = forge.registered();
+
+  let recorded = journal
+    .lock()
+    .published(registered.name, &release.tag)
+    .map(|(id, _)| id.to_string());
+  let recorded = recorded
+    .as_deref()
+    .map(|id| release_id(registered, id))
+    .transpose()?;
+
+  let existing = match recorded {
+    Some(_) => None,
+    None => find(forge, &release.tag, draft).await?,
+  };
+
+  let created = match (recorded, existing) {
+    (Some(id), _) => {
+      let created = network::retry("Getting the release", || forge.get(id)).await?;
+
+      info!("Continuing with the release at {}", F::url(&created));
+      created
+    }
+    (None, Some(existing)) if force => {
+      info!("Updating the existing release for {}", release.tag);
+
+      let updated =
+        network::retry("Updating the release", || forge.update(F::id(&existing))).await?;
+
+      info!("Updated release at {}", F::url(&updated));
+
+      updated
+    }
+    (None, Some(existing)) => {
+      info!(
+        "Reusing the existing release for {} at {}",
+        release.tag,
+        F::url(&existing)
+      );
+
+      existing
+    }
+    (None, None) => {
+      // the forge creates the tag of a published release if it isn't in the repository yet
+      let tagged =
+        draft || network::retry("Looking up the tag", || forge.tag_exists(&release.tag)).await?;
+
+      info!("Creating {} release", registered.title);
+
+      let created = network::retry_create("Creating the release", || forge.create()).await?;
+
+      info!(
+        "Created {} release{} at {}",
+        registered.title,
+        if draft { " draft" } else { "" },
+        F::url(&created)
+      );
+
+      journal.lock().created(Created::Distributed {
+        distributor: registered,
+        kind: "release",
+        id: F::id(&created).to_string(),
+        url: F::url(&created).to_string(),
+        tag: (!tagged).then(|| release.tag.clone()),
+      });
+
+      created
+    }
+  };
+
+  let id = F::id(&created).to_string();
+
+  if recorded.is_none() {
+    journal.lock().record(Step::Published {
+      distributor: registered.name.to_string(),
+      name: release.tag.clone(),
+      id: id.clone(),
+      url: F::url(&created).to_string(),
+    })?;
+  }
+
+  for asset in release.assets() {
+    if journal.lock().uploaded(registered.name, &id, &asset.name) {
+      info!("{} has already been uploaded, skipping it", asset.name);
+      continue;
+    }
+
+    if let Some((uploaded, _)) = F::assets(&created)
+      .into_iter()
+      .find(|(_, name)| *name == asset.name)
+    {
+      if !force {
+        info!("{} has already been uploaded, skipping it", asset.name);
+        continue;
+      }
+
+      info!("Replacing {}", asset.name);
+
+      network::retry(&format!("Deleting {}", asset.name), || {
+        forge.delete_asset(&created, uploaded)
+      })
+      .await?;
+    }
+
+    let bar = asset.progress(registered.title)?;
+    let uploaded = network::retry_create(&format!("Uploading {}", asset.name), || {
+      forge.upload(&created, asset, &bar)
+    })
+    .await;
+
+    progress::finish(&bar, &uploaded);
+
+    let (uploaded, url) = uploaded?;
+
+    info!("Uploaded {} to {}", asset.name, url);
+
+    journal.lock().record(Step::Uploaded {
+      distributor: registered.name.to_string(),
+      release: id.clone(),
+      name: asset.name.clone(),
+      id: uploaded.to_string(),
+    })?;
+  }
+
+  Ok(vec![F::url(&created).to_string()])
+}
+
+/// Delete a release `publish` created, along with its tag if the forge created that too
+pub async fn rollback<F: Forge>(
+  forge: &F,
+  id: &str,
+  tag: Option<&str>,
+) -> Result<(), error::AxolotlError> {
+  let id = release_id(forge.registered(), id)?;
+
+  network::retry("Deleting the release", || forge.delete(id)).await?;
+
+  if let Some(tag) = tag {
+    network::retry("Deleting the tag", || forge.delete_tag(tag)).await?;
+  }
+
+  Ok(())
+}
+
+/// The release for the tag, if there is one
+///
+/// Drafts have no tag yet, so they can't be looked up by it, and are only searched for through
+/// every page of releases when publishing drafts
+async fn find<F: Forge>(
+  forge: &F,
+  tag: &str,
+  draft: bool,
+) -> Result<Option<F::Release>, error::AxolotlError> {
+  let published = network::retry("Getting the release", || forge.get_by_tag(tag)).await?;
+
+  if published.is_some() || !draft {
+    return Ok(published);
+  }
+
+  for page in 1.. {
+    let (listed, last) = network::retry("Listing the releases", || forge.list(page)).await?;
+
+    if let Some(found) = listed.into_iter().find(|found| F::tag(found) == tag) {
+      return Ok(Some(found));
+    }
+
+    if last {
+      break;
+    }
+  }
+
+  Ok(None)
+}
+
+fn release_id(registered: &Registered, id: &str) -> Result<u64, error::AxolotlError> {
+  id.parse().map_err(|_| {
+    error::AxolotlError::Error(format!("Invalid {} release id {}", registered.title, id))
+  })
+}
@@ -0,0 +1,478 @@
+use crate::{
+  changelog,
+  configuration::{ChangelogTransformations, Configuration, Forgejo},
+  distributor::{self, Context, Distributor, Registered},
+  error,
+  forge::{self, Forge},
+  keys::Keys,
+  network,
+  publish::Asset,
+  util::VersionType,
+};
+use async_trait::async_trait;
+use indicatif::ProgressBar;
+use reqwest::{header::AUTHORIZATION, multipart::Form, Method, RequestBuilder, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+
+pub static REGISTERED: Registered = Registered {
+  name: "forgejo",
+  alias: "f",
+  title: "Forgejo",
+  create,
+};
+
+fn create<'a>(
+  configuration: &'a Configuration,
+  keys: &Keys,
+  dry_run: bool,
+) -> Option<Box<dyn Distributor + 'a>> {
+  let forgejo = configuration.forgejo.as_ref()?;
+
+  Some(Box::new(ForgejoDistributor {
+    configuration: forgejo,
+    transformations: configuration
+      .changelog
+      .as_ref()
+      .and_then(|changelog| changelog.forgejo.as_ref()),
+    links: links(forgejo),
+    token: distributor::token(keys, &REGISTERED, dry_run)?,
+    payload: serde_json::Value::Null,
+  }))
+}
+
+/// Where the changelog links to, the `[forgejo]` repository
+pub fn links(forgejo: &Forgejo) -> changelog::Links {
+  let host = forgejo.url.trim_end_matches('/');
+  let (owner, repo) = &forgejo.repo;
+
+  changelog::Links {
+    host: host.to_string(),
+    repository: format!("{}/{}/{}", host, owner, repo),
+    prefix: "",
+  }
+}
+
+/// Creates a release in the `[forgejo]` repository (on Forgejo or Gitea) and attaches every
+/// artifact to it
+pub struct ForgejoDistributor<'a> {
+  configuration: &'a Forgejo,
+  transformations: Option<&'a ChangelogTransformations>,
+  links: changelog::Links,
+  /// Only missing for a dry run
+  token: Option<String>,
+  /// The release as it is sent to the instance, built by `prepare`
+  payload: serde_json::Value,
+}
+
+/// The most releases an instance lists at once, unless it is configured otherwise
+const RELEASES_PER_PAGE: usize = 50;
+
+#[derive(Debug, Deserialize)]
+struct Release {
+  id: u64,
+  tag_name: String,
+  html_url: String,
+  #[serde(default)]
+  assets: Vec<Attachment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Attachment {
+  id: u64,
+  name: String,
+  browser_download_url: String,
+}
+
+#[async_trait]
+impl Distributor for ForgejoDistributor<'_> {
+  fn registered(&self) -> &'static Registered {
+    &REGISTERED
+  }
+
+  async fn prepare(&mut self, context: &Context<'_, '_>) -> Result<(), error::AxolotlError> {
+    let Forgejo {
+      draft, prerelease, ..
+    } = self.configuration;
+    let release = context.release;
+
+    self.payload = json!({
+      "tag_name": release.tag,
+      "target_commitish": release.commit,
+      "name": release.tag,
+      "body": context
+        .changelog(self.transformations, Some(&self.links))?.unwrap_or_default(),
+      "draft": draft,
+      "prerelease": prerelease.unwrap_or(release.version_type != VersionType::Release),
+    });
+
+    // the default branch is used if no commit is given
+    if let Some(payload) = self.payload.as_object_mut() {
+      payload.retain(|_, value| !value.is_null());
+    }
+
+    Ok(())
+  }
+
+  async fn publish(&self, context: &Context<'_, '_>) -> Result<Vec<String>, error::AxolotlError> {
+    let Forgejo {
+      url, repo, draft, ..
+    } = self.configuration;
+
+    if context.dry_run {
+      info!(
+        "Would create Forgejo release in {}/{} on {}\n{}\nwith the attachments {}",
+        repo.0,
+        repo.1,
+        url,
+        serde_json::to_string_pretty(&self.payload)?,
+        context
+          .release
+          .assets()
+          .map(|asset| asset.name.as_str())
+          .collect::<Vec<_>>()
+          .join(", ")
+      );
+
+      return Ok(vec![]);
+    }
+
+    forge::publish(&self.releases()?, context, *draft).await
+  }
+
+  async fn rollback(
+    &self,
+    _kind: &str,
+    id: &str,
+    tag: Option<&str>,
+  ) -> Result<(), error::AxolotlError> {
+    forge::rollback(&self.releases()?, id, tag).await
+  }
+}
+
+impl ForgejoDistributor<'_> {
+  fn releases(&self) -> Result<Releases<'_>, error::AxolotlError> {
+    Ok(Releases {
+      distributor: self,
+      client: network::client()?,
+      token: distributor::key(&self.token, &REGISTERED)?,
+    })
+  }
+}
+
+/// The releases of the `[forgejo]` repository
+struct Releases<'a> {
+  distributor: &'a ForgejoDistributor<'a>,
+  client: reqwest::Client,
+  token: &'a str,
+}
+
+impl Releases<'_> {
+  /// A request to the API of the repository, authenticated with the token
+  fn request(&self, method: Method, path: &str) -> RequestBuilder {
+    let Forgejo {
+      url,
+      repo: (owner, repo),
+      ..
+    } = self.distributor.configuration;
+
+    self
+      .client
+      .request(
+        method,
+        format!(
+          "{}/api/v1/repos/{}/{}{}",
+          url.trim_end_matches('/'),
+          owner,
+          repo,
+          path
+        ),
+      )
+      .header(AUTHORIZATION, format!("token {}", self.token))
+  }
+}
+
+#[async_trait]
+impl Forge for Releases<'_> {
+  type Release = Release;
+
+  fn registered(&self) -> &'static Registered {
+    &REGISTERED
+  }
+
+  fn id(release: &Release) -> u64 {
+    release.id
+  }
+
+  fn tag(release: &Release) -> &str {
+    &release.tag_name
+  }
+
+  fn url(release: &Release) -> &str {
+    &release.html_url
+  }
+
+  fn assets(release: &Release) -> Vec<(u64, &str)> {
+    release
+      .assets
+      .iter()
+      .map(|attachment| (attachment.id, attachment.name.as_str()))
+      .collect()
+  }
+
+  async fn get_by_tag(&self, tag: &str) -> Result<Option<Release>, error::AxolotlError> {
+    match send(self.request(
+      Method::GET,
+      &format!("/releases/tags/{}", network::encode(tag)),
+    ))
+    .await
+    {
+      Ok(content) => Ok(Some(parse(content)?)),
+      Err(error) if not_found(&error) => Ok(None),
+      Err(error) => Err(error),
+    }
+  }
+
+  async fn list(&self, page: u32) -> Result<(Vec<Release>, bool), error::AxolotlError> {
+    let listed: Vec<Release> = parse(
+      send(
+        self
+          .request(Method::GET, "/releases")
+          .query(&[("page", page as usize), ("limit", RELEASES_PER_PAGE)]),
+      )
+      .await?,
+    )?;
+    let last = listed.len() < RELEASES_PER_PAGE;
+
+    Ok((listed, last))
+  }
+
+  async fn get(&self, id: u64) -> Result<Release, error::AxolotlError> {
+    parse(send(self.request(Method::GET, &format!("/releases/{}", id))).await?)
+  }
+
+  async fn create(&self) -> Result<Release, error::AxolotlError> {
+    parse(
+      send(
+        self
+          .request(Method::POST, "/releases")
+          .json(&self.distributor.payload),
+      )
+      .await?,
+    )
+  }
+
+  async fn update(&self, id: u64) -> Result<Release, error::AxolotlError> {
+    parse(
+      send(
+        self
+          .request(Method::PATCH, &format!("/releases/{}", id))
+          .json(&self.distributor.payload),
+      )
+      .await?,
+    )
+  }
+
+  async fn delete(&self, id: u64) -> Result<(), error::AxolotlError> {
+    send(self.request(Method::DELETE, &format!("/releases/{}", id)))
+      .await
+      .map(|_| ())
+  }
+
+  async fn tag_exists(&self, tag: &str) -> Result<bool, error::AxolotlError> {
+    match send(self.request(Method::GET, &format!("/tags/{}", network::encode(tag)))).await {
+      Ok(_) => Ok(true),
+      Err(error) if not_found(&error) => Ok(false),
+      Err(error) => Err(error),
+    }
+  }
+
+  async fn delete_tag(&self, tag: &str) -> Result<(), error::AxolotlError> {
+    send(self.request(Method::DELETE, &format!("/tags/{}", network::encode(tag))))
+      .await
+      .map(|_| ())
+  }
+
+  /// Stream an asset to the release as an attachment
+  async fn upload(
+    &self,
+    release: &Release,
+    asset: &Asset,
+    progress: &ProgressBar,
+  ) -> Result<(u64, String), error::AxolotlError> {
+    let form = Form::new().part("attachment", asset.part(progress).await?);
+    let attachment: Attachment = parse(
+      send(
+        self
+          .request(Method::POST, &format!("/releases/{}/assets", release.id))
+          .query(&[("name", &asset.name)])
+          .multipart(form),
+      )
+      .await?,
+    )?;
+
+    Ok((attachment.id, attachment.browser_download_url))
+  }
+
+  async fn delete_asset(&self, release: &Release, id: u64) -> Result<(), error::AxolotlError> {
+    send(self.request(
+      Method::DELETE,
+      &format!("/releases/{}/assets/{}", release.id, id),
+    ))
+    .await
+    .map(|_| ())
+  }
+}
+
+fn not_found(error: &error::AxolotlError) -> bool {
+  matches!(
+    error,
+    error::AxolotlError::ForgejoError { status, .. } if *status == StatusCode::NOT_FOUND
+  )
+}
+
+async fn send(request: RequestBuilder) -> Result<String, error::AxolotlError> {
+  network::send(request, |status, content| {
+    error::AxolotlError::ForgejoError { status, content }
+  })
+  .await
+}
+
+fn parse<T: for<'de> Deserialize<'de>>(content: String) -> Result<T, error::AxolotlError> {
+  Ok(serde_json::from_str(&content)?)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use wiremock::{
+    matchers::{header, method, path},
+    Mock, MockServer, ResponseTemplate,
+  };
+
+  fn configuration(url: &str) -> Forgejo {
+    Forgejo {
+      url: format!("{}/", url),
+      repo: ("owner".to_string(), "repo".to_string()),
+      draft: false,
+      prerelease: None,
+    }
+  }
+
+  fn distributor(configuration: &Forgejo) -> ForgejoDistributor<'_> {
+    ForgejoDistributor {
+      configuration,
+      transformations: None,
+      links: links(configuration),
+      token: Some("token".to_string()),
+      payload: serde_json::Value::Null,
+    }
+  }
+
+  #[test]
+  fn links_point_to_the_repository_on_the_instance() {
+    let links = links(&configuration("https://codeberg.org"));
+
+    assert_eq!(links.host, "https://codeberg.org");
+    assert_eq!(links.repository, "https://codeberg.org/owner/repo");
+    assert_eq!(links.prefix, "");
+  }
+
+  #[tokio::test]
+  async fn get_by_tag_encodes_the_tag() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+      .and(path(
+        "/api/v1/repos/owner/repo/releases/tags/mc1.21%2F1.0.0",
+      ))
+      .and(header("authorization", "token token"))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+        "id": 7,
+        "tag_name": "mc1.21/1.0.0",
+        "html_url": "https://codeberg.org/owner/repo/releases/tag/mc1.21/1.0.0",
+      })))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let configuration = configuration(&server.uri());
+    let distributor = distributor(&configuration);
+    let release = distributor
+      .releases()
+      .unwrap()
+      .get_by_tag("mc1.21/1.0.0")
+      .await
+      .unwrap()
+      .unwrap();
+
+    assert_eq!(release.id, 7);
+    assert!(release.assets.is_empty());
+  }
+
+  #[tokio::test]
+  async fn get_by_tag_is_missing_for_unknown_tags() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+      .respond_with(ResponseTemplate::new(404))
+      .mount(&server)
+      .await;
+
+    let configuration = configuration(&server.uri());
+    let distributor = distributor(&configuration);
+
+    assert!(distributor
+      .releases()
+      .unwrap()
+      .get_by_tag("v1.0.0")
+      .await
+      .unwrap()
+      .is_none());
+  }
+
+  #[tokio::test]
+  async fn tags_are_found_and_deleted_by_their_encoded_name() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+      .and(path("/api/v1/repos/owner/repo/tags/v1.0.0%2Bbuild"))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+      .mount(&server)
+      .await;
+    Mock::given(method("GET"))
+      .and(path("/api/v1/repos/owner/repo/tags/v2.0.0"))
+      .respond_with(ResponseTemplate::new(404))
+      .mount(&server)
+      .await;
+    Mock::given(method("DELETE"))
+      .and(path("/api/v1/repos/owner/repo/tags/v1.0.0%2Bbuild"))
+      .respond_with(ResponseTemplate::new(204))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let configuration = configuration(&server.uri());
+    let distributor = distributor(&configuration);
+    let releases = distributor.releases().unwrap();
+
+    assert!(releases.tag_exists("v1.0.0+build").await.unwrap());
+    assert!(!releases.tag_exists("v2.0.0").await.unwrap());
+    releases.delete_tag("v1.0.0+build").await.unwrap();
+  }
+
+  #[tokio::test]
+  async fn errors_keep_the_status() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+      .respond_with(ResponseTemplate::new(401).set_body_string("unauthorized"))
+      .mount(&server)
+      .await;
+
+    let configuration = configuration(&server.uri());
+    let distributor = distributor(&configuration);
+
+    assert!(matches!(
+      distributor.releases().unwrap().get(7).await,
+      Err(error::AxolotlError::ForgejoError { status, content })
+        if status == StatusCode::UNAUTHORIZED && content == "unauthorized"
+    ));
+  }
+}
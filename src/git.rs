@@ -0,0 +1,253 @@
+use crate::{constants, error};
+use std::process::Command;
+
+fn git(arguments: &[&str]) -> Result<(bool, String), error::AxolotlError> {
+  let output = Command::new("git").args(arguments).output()?;
+
+  let stream = if output.status.success() {
+    output.stdout
+  } else {
+    output.stderr
+  };
+
+  Ok((
+    output.status.success(),
+    String::from_utf8(stream)?.trim().to_string(),
+  ))
+}
+
+fn git_ok(arguments: &[&str]) -> Result<String, error::AxolotlError> {
+  match git(arguments)? {
+    (true, output) => Ok(output),
+    (false, output) => Err(error::AxolotlError::Error(format!(
+      "`git {}` failed: {}",
+      arguments.join(" "),
+      output
+    ))),
+  }
+}
+
+/// The commit hash of HEAD
+pub fn head() -> Result<String, error::AxolotlError> {
+  git_ok(&["rev-parse", "HEAD"])
+}
+
+/// Whether the working tree has no uncommitted changes (untracked files included), ignoring the
+/// publish state kept by axolotl
+pub fn is_clean() -> Result<bool, error::AxolotlError> {
+  Ok(
+    git_ok(&[
+      "status",
+      "--porcelain",
+      "--",
+      ".",
+      &format!(":!{}", constants::STATE_FOLDER),
+    ])?
+    .is_empty(),
+  )
+}
+
+/// The commit the tag points to, if it exists
+pub fn tag_commit(tag: &str) -> Result<Option<String>, error::AxolotlError> {
+  match git(&[
+    "rev-parse",
+    "--quiet",
+    "--verify",
+    &format!("refs/tags/{}^{{commit}}", tag),
+  ])? {
+    (true, commit) => Ok(Some(commit)),
+    (false, _) => Ok(None),
+  }
+}
+
+/// Create an annotated tag on HEAD
+pub fn create_tag(tag: &str, message: &str) -> Result<(), error::AxolotlError> {
+  // the default cleanup strips lines starting with `#`, such as markdown headings, as comments
+  git_ok(&[
+    "tag",
+    "--annotate",
+    tag,
+    "--cleanup=whitespace",
+    "--message",
+    message,
+  ])
+  .map(|_| ())
+}
+
+pub fn push_tag(remote: &str, tag: &str) -> Result<(), error::AxolotlError> {
+  git_ok(&["push", remote, &format!("refs/tags/{}", tag)]).map(|_| ())
+}
+
+pub fn delete_tag(tag: &str) -> Result<(), error::AxolotlError> {
+  git_ok(&["tag", "--delete", tag]).map(|_| ())
+}
+
+pub fn delete_remote_tag(remote: &str, tag: &str) -> Result<(), error::AxolotlError> {
+  git_ok(&["push", "--delete", remote, &format!("refs/tags/{}", tag)]).map(|_| ())
+}
+
+/// The most recent tag reachable from HEAD, other than the given one
+pub fn previous_tag(exclude: &str) -> Result<Option<String>, error::AxolotlError> {
+  match git(&["describe", "--tags", "--abbrev=0", "--exclude", exclude])? {
+    (true, tag) => Ok(Some(tag)),
+    (false, _) => Ok(None),
+  }
+}
+
+/// The subjects of the non-merge commits after `since` (or all of them) up to HEAD, newest first
+pub fn subjects(since: Option<&str>) -> Result<Vec<String>, error::AxolotlError> {
+  let range = match since {
+    Some(since) => format!("{}..HEAD", since),
+    None => "HEAD".to_string(),
+  };
+
+  Ok(
+    git_ok(&["log", "--no-merges", "--format=%s", &range])?
+      .lines()
+      .map(str::to_string)
+      .collect(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::{
+    env, fs,
+    sync::{Mutex, MutexGuard},
+  };
+  use tempfile::TempDir;
+
+  /// git runs in the current directory, which every test shares
+  static CURRENT_DIRECTORY: Mutex<()> = Mutex::new(());
+
+  /// A repository with a single commit, used as the current directory for as long as it lives
+  struct Repository {
+    directory: TempDir,
+    _lock: MutexGuard<'static, ()>,
+  }
+
+  impl Repository {
+    fn new() -> Self {
+      let lock = CURRENT_DIRECTORY
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+      let directory = tempfile::tempdir().unwrap();
+      env::set_current_dir(directory.path()).unwrap();
+
+      git_ok(&["init", "--quiet"]).unwrap();
+      git_ok(&["config", "user.name", "axolotl"]).unwrap();
+      git_ok(&["config", "user.email", "axolotl@example.com"]).unwrap();
+      git_ok(&["config", "commit.gpgSign", "false"]).unwrap();
+      git_ok(&["config", "tag.gpgSign", "false"]).unwrap();
+
+      let repository = Repository {
+        directory,
+        _lock: lock,
+      };
+      repository.commit("README.md", "feat: initial commit");
+      repository
+    }
+
+    fn write(&self, file: &str, contents: &str) {
+      fs::write(self.directory.path().join(file), contents).unwrap();
+    }
+
+    fn commit(&self, file: &str, subject: &str) {
+      self.write(file, subject);
+      git_ok(&["add", file]).unwrap();
+      git_ok(&["commit", "--quiet", "--message", subject]).unwrap();
+    }
+  }
+
+  #[test]
+  fn is_clean_sees_untracked_and_modified_files() {
+    let repository = Repository::new();
+    assert!(is_clean().unwrap());
+
+    repository.write("build.gradle", "");
+    assert!(!is_clean().unwrap());
+
+    repository.commit("build.gradle", "build: add gradle");
+    assert!(is_clean().unwrap());
+
+    repository.write("README.md", "changed");
+    assert!(!is_clean().unwrap());
+  }
+
+  #[test]
+  fn create_tag_annotates_head() {
+    let _repository = Repository::new();
+    assert_eq!(tag_commit("v1.0.0").unwrap(), None);
+
+    create_tag("v1.0.0", "Release 1.0.0").unwrap();
+
+    assert_eq!(tag_commit("v1.0.0").unwrap(), Some(head().unwrap()));
+    assert_eq!(git_ok(&["cat-file", "-t", "v1.0.0"]).unwrap(), "tag");
+  }
+
+  #[test]
+  fn previous_tag_skips_the_given_tag() {
+    let repository = Repository::new();
+    assert_eq!(previous_tag("v1.0.0").unwrap(), None);
+
+    create_tag("v1.0.0", "Release 1.0.0").unwrap();
+    repository.commit("CHANGELOG.md", "fix: a bug");
+    create_tag("v1.1.0", "Release 1.1.0").unwrap();
+
+    assert_eq!(previous_tag("v1.1.0").unwrap(), Some("v1.0.0".to_string()));
+    assert_eq!(previous_tag("v1.2.0").unwrap(), Some("v1.1.0".to_string()));
+  }
+
+  #[test]
+  fn subjects_since_a_tag_leave_out_merges() {
+    let repository = Repository::new();
+    create_tag("v1.0.0", "Release 1.0.0").unwrap();
+    let branch = git_ok(&["branch", "--show-current"]).unwrap();
+
+    git_ok(&["checkout", "--quiet", "-b", "feature"]).unwrap();
+    repository.commit("feature.txt", "feat: a feature");
+    git_ok(&["checkout", "--quiet", &branch]).unwrap();
+    repository.commit("fix.txt", "fix: a bug");
+    git_ok(&["merge", "--quiet", "--no-ff", "--no-edit", "feature"]).unwrap();
+
+    let mut since = subjects(Some("v1.0.0")).unwrap();
+    since.sort();
+
+    assert_eq!(since, ["feat: a feature", "fix: a bug"]);
+    assert_eq!(subjects(None).unwrap().len(), 3);
+  }
+
+  #[test]
+  fn create_tag_keeps_markdown_headings() {
+    let _repository = Repository::new();
+    let message = "## Features\n\n- A feature\n\n## Bug Fixes\n\n- # A fix";
+
+    create_tag("v1.0.0", message).unwrap();
+
+    assert_eq!(
+      git_ok(&["tag", "--list", "--format=%(contents)", "v1.0.0"]).unwrap(),
+      message
+    );
+  }
+
+  #[test]
+  fn delete_tag_removes_the_tag() {
+    let _repository = Repository::new();
+    create_tag("v1.0.0", "Release 1.0.0").unwrap();
+
+    delete_tag("v1.0.0").unwrap();
+
+    assert_eq!(tag_commit("v1.0.0").unwrap(), None);
+    assert!(delete_tag("v1.0.0").is_err());
+  }
+
+  #[test]
+  fn create_tag_fails_if_the_tag_exists() {
+    let _repository = Repository::new();
+
+    create_tag("v1.0.0", "Release 1.0.0").unwrap();
+
+    assert!(create_tag("v1.0.0", "Release 1.0.0").is_err());
+  }
+}
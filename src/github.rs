@@ -0,0 +1,345 @@
+use crate::{
+  changelog,
+  configuration::{ChangelogTransformations, Configuration, GitHub},
+  distributor::{self, Context, Distributor, Registered},
+  error,
+  forge::{self, Forge},
+  keys::Keys,
+  network,
+  publish::Asset,
+  util::VersionType,
+};
+use async_trait::async_trait;
+use indicatif::ProgressBar;
+use octocrab::{params::repos::Reference, repos::RepoHandler, Octocrab};
+use reqwest::header::{ACCEPT, CONTENT_LENGTH, CONTENT_TYPE};
+use serde_json::json;
+
+pub static REGISTERED: Registered = Registered {
+  name: "github",
+  alias: "g",
+  title: "GitHub",
+  create,
+};
+
+fn create<'a>(
+  configuration: &'a Configuration,
+  keys: &Keys,
+  dry_run: bool,
+) -> Option<Box<dyn Distributor + 'a>> {
+  let github = configuration.github.as_ref()?;
+
+  Some(Box::new(GitHubDistributor {
+    configuration: github,
+    transformations: configuration
+      .changelog
+      .as_ref()
+      .and_then(|changelog| changelog.github.as_ref()),
+    token: distributor::token(keys, &REGISTERED, dry_run)?,
+    payload: serde_json::Value::Null,
+  }))
+}
+
+/// Where the changelog links to, the `[github]` repository
+pub fn links(github: &GitHub) -> changelog::Links {
+  let (user, repo) = &github.repo;
+
+  changelog::Links {
+    host: "https://github.com".to_string(),
+    repository: format!("https://github.com/{}/{}", user, repo),
+    prefix: "",
+  }
+}
+
+/// Creates a release in the `[github]` repository and uploads every artifact to it
+pub struct GitHubDistributor<'a> {
+  configuration: &'a GitHub,
+  transformations: Option<&'a ChangelogTransformations>,
+  /// Only missing for a dry run
+  token: Option<String>,
+  /// The release as it is sent to GitHub, built by `prepare`
+  payload: serde_json::Value,
+}
+
+#[async_trait]
+impl Distributor for GitHubDistributor<'_> {
+  fn registered(&self) -> &'static Registered {
+    &REGISTERED
+  }
+
+  async fn prepare(&mut self, context: &Context<'_, '_>) -> Result<(), error::AxolotlError> {
+    let GitHub {
+      draft,
+      name,
+      target_commitish,
+      make_latest,
+      generate_release_notes,
+      discussion_category_name,
+      ..
+    } = self.configuration;
+    let release = context.release;
+
+    let name = match name {
+      Some(template) => changelog::render(template, release)?,
+      None => release.tag.clone(),
+    };
+
+    // sent as is rather than through octocrab's builder, which lacks some of the options
+    self.payload = json!({
+      "tag_name": release.tag,
+      "target_commitish": target_commitish.as_ref().or(release.commit.as_ref()),
+      "name": name,
+      "body": context
+        .changelog(self.transformations, Some(&links(self.configuration)))?.unwrap_or_default(),
+      "draft": draft,
+      "prerelease": release.version_type != VersionType::Release,
+      "make_latest": make_latest,
+      "generate_release_notes": generate_release_notes,
+      "discussion_category_name": discussion_category_name,
+    });
+
+    // GitHub rejects nulls for options that are left out
+    if let Some(payload) = self.payload.as_object_mut() {
+      payload.retain(|_, value| !value.is_null());
+    }
+
+    Ok(())
+  }
+
+  async fn publish(&self, context: &Context<'_, '_>) -> Result<Vec<String>, error::AxolotlError> {
+    let (user, repo) = &self.configuration.repo;
+
+    if context.dry_run {
+      info!(
+        "Would create GitHub release in {}/{}\n{}\nwith the assets {}",
+        user,
+        repo,
+        serde_json::to_string_pretty(&self.payload)?,
+        context
+          .release
+          .assets()
+          .map(|asset| asset.name.as_str())
+          .collect::<Vec<_>>()
+          .join(", ")
+      );
+
+      return Ok(vec![]);
+    }
+
+    forge::publish(&self.releases()?, context, self.configuration.draft).await
+  }
+
+  async fn rollback(
+    &self,
+    _kind: &str,
+    id: &str,
+    tag: Option<&str>,
+  ) -> Result<(), error::AxolotlError> {
+    forge::rollback(&self.releases()?, id, tag).await
+  }
+}
+
+impl GitHubDistributor<'_> {
+  fn releases(&self) -> Result<Releases<'_>, error::AxolotlError> {
+    let (user, repo) = &self.configuration.repo;
+    let token = distributor::key(&self.token, &REGISTERED)?;
+
+    Ok(Releases {
+      octocrab: network::github(token.to_string())?,
+      client: network::client()?,
+      user,
+      repo,
+      token,
+      payload: &self.payload,
+    })
+  }
+}
+
+/// The releases of the `[github]` repository
+struct Releases<'a> {
+  octocrab: Octocrab,
+  client: reqwest::Client,
+  user: &'a str,
+  repo: &'a str,
+  token: &'a str,
+  payload: &'a serde_json::Value,
+}
+
+impl Releases<'_> {
+  fn repo(&self) -> RepoHandler<'_> {
+    self.octocrab.repos(self.user, self.repo)
+  }
+
+  fn route(&self) -> String {
+    format!("/repos/{}/{}/releases", self.user, self.repo)
+  }
+}
+
+#[async_trait]
+impl Forge for Releases<'_> {
+  type Release = octocrab::models::repos::Release;
+
+  fn registered(&self) -> &'static Registered {
+    &REGISTERED
+  }
+
+  fn id(release: &Self::Release) -> u64 {
+    release.id.0
+  }
+
+  fn tag(release: &Self::Release) -> &str {
+    &release.tag_name
+  }
+
+  fn url(release: &Self::Release) -> &str {
+    release.html_url.as_str()
+  }
+
+  fn assets(release: &Self::Release) -> Vec<(u64, &str)> {
+    release
+      .assets
+      .iter()
+      .map(|asset| (asset.id.0, asset.name.as_str()))
+      .collect()
+  }
+
+  async fn get_by_tag(&self, tag: &str) -> Result<Option<Self::Release>, error::AxolotlError> {
+    match self.repo().releases().get_by_tag(tag).await {
+      Ok(release) => Ok(Some(release)),
+      Err(octocrab::Error::GitHub { source, .. }) if source.status_code == 404 => Ok(None),
+      Err(error) => Err(error.into()),
+    }
+  }
+
+  async fn list(&self, page: u32) -> Result<(Vec<Self::Release>, bool), error::AxolotlError> {
+    let listed = self
+      .repo()
+      .releases()
+      .list()
+      .per_page(100u8)
+      .page(page)
+      .send()
+      .await?;
+    let last = listed.next.is_none();
+
+    Ok((listed.items, last))
+  }
+
+  async fn get(&self, id: u64) -> Result<Self::Release, error::AxolotlError> {
+    Ok(self.repo().releases().get(id).await?)
+  }
+
+  async fn create(&self) -> Result<Self::Release, error::AxolotlError> {
+    send_release(&self.octocrab, &self.route(), None, self.payload).await
+  }
+
+  async fn update(&self, id: u64) -> Result<Self::Release, error::AxolotlError> {
+    let mut payload = self.payload.clone();
+
+    // generated notes can only be requested when creating a release
+    if let Some(payload) = payload.as_object_mut() {
+      payload.remove("generate_release_notes");
+    }
+
+    send_release(&self.octocrab, &self.route(), Some(id), &payload).await
+  }
+
+  async fn delete(&self, id: u64) -> Result<(), error::AxolotlError> {
+    Ok(self.repo().releases().delete(id).await?)
+  }
+
+  async fn tag_exists(&self, tag: &str) -> Result<bool, error::AxolotlError> {
+    match self.repo().get_ref(&Reference::Tag(tag.to_string())).await {
+      Ok(_) => Ok(true),
+      Err(octocrab::Error::GitHub { source, .. }) if source.status_code == 404 => Ok(false),
+      Err(error) => Err(error.into()),
+    }
+  }
+
+  async fn delete_tag(&self, tag: &str) -> Result<(), error::AxolotlError> {
+    Ok(
+      self
+        .repo()
+        .delete_ref(&Reference::Tag(tag.to_string()))
+        .await?,
+    )
+  }
+
+  async fn upload(
+    &self,
+    release: &Self::Release,
+    asset: &Asset,
+    progress: &ProgressBar,
+  ) -> Result<(u64, String), error::AxolotlError> {
+    let uploaded = upload_asset(&self.client, self.token, release, asset, progress).await?;
+
+    Ok((uploaded.id.0, uploaded.browser_download_url.to_string()))
+  }
+
+  async fn delete_asset(
+    &self,
+    _release: &Self::Release,
+    id: u64,
+  ) -> Result<(), error::AxolotlError> {
+    Ok(self.repo().release_assets().delete(id).await?)
+  }
+}
+
+/// Create a release, or update it if an id is given, keeping the wait asked for by a rate limited
+/// response so that the retry honors it
+async fn send_release(
+  octocrab: &Octocrab,
+  route: &str,
+  id: Option<u64>,
+  payload: &serde_json::Value,
+) -> Result<octocrab::models::repos::Release, error::AxolotlError> {
+  let response = match id {
+    Some(id) => {
+      octocrab
+        ._patch(format!("{}/{}", route, id), Some(payload))
+        .await?
+    }
+    None => octocrab._post(route, Some(payload)).await?,
+  };
+
+  let wait = network::rate_limit(response.headers());
+  let response = octocrab::map_github_error(response)
+    .await
+    .map_err(|error| network::rate_limited(error.into(), wait))?;
+
+  Ok(serde_json::from_str(
+    &octocrab.body_to_string(response).await?,
+  )?)
+}
+
+/// Stream an asset to the release, which octocrab can only do from memory
+async fn upload_asset(
+  client: &reqwest::Client,
+  token: &str,
+  release: &octocrab::models::repos::Release,
+  asset: &Asset,
+  progress: &ProgressBar,
+) -> Result<octocrab::models::repos::Asset, error::AxolotlError> {
+  // the upload URL is a template ending in {?name,label}
+  let url = release
+    .upload_url
+    .split('{')
+    .next()
+    .unwrap_or(&release.upload_url);
+
+  let request = client
+    .post(url)
+    .query(&[("name", &asset.name), ("label", &asset.name)])
+    .bearer_auth(token)
+    .header(ACCEPT, "application/vnd.github+json")
+    .header(CONTENT_TYPE, "application/octet-stream")
+    .header(CONTENT_LENGTH, asset.length()?)
+    .body(asset.stream(progress).await?);
+
+  Ok(serde_json::from_str(
+    &network::send(request, |status, content| {
+      error::AxolotlError::GitHubUploadError { status, content }
+    })
+    .await?,
+  )?)
+}
@@ -0,0 +1,761 @@
+use crate::{
+  changelog,
+  configuration::{ChangelogTransformations, Configuration, GitLab},
+  distributor::{self, Context, Distributor, Registered},
+  error,
+  journal::Step,
+  keys::Keys,
+  network, progress,
+  publish::Asset,
+  rollback::Created,
+};
+use async_trait::async_trait;
+use indicatif::ProgressBar;
+use reqwest::{header::CONTENT_LENGTH, Method, RequestBuilder, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+
+pub static REGISTERED: Registered = Registered {
+  name: "gitlab",
+  alias: "gl",
+  title: "GitLab",
+  create,
+};
+
+fn create<'a>(
+  configuration: &'a Configuration,
+  keys: &Keys,
+  dry_run: bool,
+) -> Option<Box<dyn Distributor + 'a>> {
+  let gitlab = configuration.gitlab.as_ref()?;
+
+  Some(Box::new(GitLabDistributor {
+    configuration: gitlab,
+    transformations: configuration
+      .changelog
+      .as_ref()
+      .and_then(|changelog| changelog.gitlab.as_ref()),
+    links: links(gitlab),
+    token: distributor::token(keys, &REGISTERED, dry_run)?,
+    package: String::new(),
+    payload: serde_json::Value::Null,
+  }))
+}
+
+/// Where the changelog links to, the `[gitlab]` project, unless it is configured by its id
+pub fn links(gitlab: &GitLab) -> Option<changelog::Links> {
+  if gitlab.project.bytes().all(|byte| byte.is_ascii_digit()) {
+    return None;
+  }
+
+  let host = gitlab.url.trim_end_matches('/');
+
+  Some(links_to(
+    host,
+    format!("{}/{}", host, gitlab.project.trim_matches('/')),
+  ))
+}
+
+fn links_to(host: &str, repository: String) -> changelog::Links {
+  changelog::Links {
+    host: host.trim_end_matches('/').to_string(),
+    repository,
+    prefix: "/-",
+  }
+}
+
+/// Uploads every artifact to the generic package registry of the `[gitlab]` project, then creates a
+/// release for the tag linking to them
+pub struct GitLabDistributor<'a> {
+  configuration: &'a GitLab,
+  transformations: Option<&'a ChangelogTransformations>,
+  /// Missing for a project configured by its id until `prepare` looks it up
+  links: Option<changelog::Links>,
+  /// Only missing for a dry run
+  token: Option<String>,
+  /// The name of the package, looked up by `prepare` along with the rest
+  package: String,
+  /// The release as it is sent to GitLab, without the links to the packages
+  payload: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct Project {
+  path: String,
+  web_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Package {
+  id: u64,
+  version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageFile {
+  id: u64,
+  package_id: u64,
+  file_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Release {
+  #[serde(rename = "_links")]
+  links: ReleaseLinks,
+  #[serde(default)]
+  assets: ReleaseAssets,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseLinks {
+  #[serde(rename = "self")]
+  url: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ReleaseAssets {
+  #[serde(default)]
+  links: Vec<ReleaseLink>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseLink {
+  name: String,
+}
+
+#[async_trait]
+impl Distributor for GitLabDistributor<'_> {
+  fn registered(&self) -> &'static Registered {
+    &REGISTERED
+  }
+
+  async fn prepare(&mut self, context: &Context<'_, '_>) -> Result<(), error::AxolotlError> {
+    let release = context.release;
+    let client = network::client()?;
+
+    // a private project can't be looked up without a token, which only a dry run goes without, so
+    // the package is named after the end of the configured path then
+    let path = match &self.token {
+      Some(_) => {
+        let project = network::retry("Looking up the project", || async {
+          parse::<Project>(send(self.request(&client, Method::GET, "")).await?)
+        })
+        .await?;
+
+        self.links = Some(links_to(&self.configuration.url, project.web_url));
+        project.path
+      }
+      None => self
+        .configuration
+        .project
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .to_string(),
+    };
+
+    self.package = self.configuration.package.clone().unwrap_or(path);
+    self.payload = json!({
+      "tag_name": release.tag,
+      "name": release.tag,
+      "description": context
+        .changelog(self.transformations, self.links.as_ref())?.unwrap_or_default(),
+      "ref": self.configuration.reference.as_ref().or(release.commit.as_ref()),
+    });
+
+    // GitLab only needs a ref if the tag doesn't exist yet
+    if let Some(payload) = self.payload.as_object_mut() {
+      payload.retain(|_, value| !value.is_null());
+    }
+
+    // without a ref the tag has to be there already, which is better found out before uploading
+    if self.payload.get("ref").is_none()
+      && self.token.is_some()
+      && !network::retry("Looking up the tag", || {
+        self.tag_exists(&client, &release.tag)
+      })
+      .await?
+    {
+      return Err(error::AxolotlError::Error(format!(
+        "The tag {} does not exist in the GitLab project - set ref in [gitlab] or enable [git]",
+        release.tag
+      )));
+    }
+
+    Ok(())
+  }
+
+  async fn publish(&self, context: &Context<'_, '_>) -> Result<Vec<String>, error::AxolotlError> {
+    let Context {
+      release,
+      journal,
+      force,
+      dry_run,
+      ..
+    } = *context;
+    let tag = &release.tag;
+    let version = &release.version;
+
+    if dry_run {
+      info!(
+        "Would upload {} to the GitLab package {} {} in {} and create GitLab release\n{}",
+        release
+          .assets()
+          .map(|asset| asset.name.as_str())
+          .collect::<Vec<_>>()
+          .join(", "),
+        self.package,
+        version,
+        self.configuration.project,
+        serde_json::to_string_pretty(&self.payload)?
+      );
+
+      return Ok(vec![]);
+    }
+
+    let client = network::client()?;
+
+    let existing = network::retry("Listing the package files", || {
+      self.package_files(&client, version)
+    })
+    .await?;
+
+    for asset in release.assets() {
+      if journal.lock().uploaded(REGISTERED.name, tag, &asset.name) {
+        info!("{} has already been uploaded, skipping it", asset.name);
+        continue;
+      }
+
+      if let Some(file) = existing.iter().find(|file| file.file_name == asset.name) {
+        if !force {
+          info!("{} has already been uploaded, skipping it", asset.name);
+          continue;
+        }
+
+        info!("Replacing {}", asset.name);
+
+        network::retry(&format!("Deleting {}", asset.name), || {
+          self.delete_package_file(&client, file.package_id, file.id)
+        })
+        .await?;
+      }
+
+      let bar = asset.progress(REGISTERED.title)?;
+      let uploaded = network::retry_create(&format!("Uploading {}", asset.name), || {
+        self.upload(&client, version, asset, &bar)
+      })
+      .await;
+
+      progress::finish(&bar, &uploaded);
+
+      let uploaded = uploaded?;
+      let url = self.package_url(version, &asset.name);
+      let id = format!("{}/{}", uploaded.package_id, uploaded.id);
+
+      info!("Uploaded {} to {}", asset.name, url);
+
+      journal.lock().created(Created::Distributed {
+        distributor: &REGISTERED,
+        kind: "package file",
+        id: id.clone(),
+        url,
+        tag: None,
+      });
+      journal.lock().record(Step::Uploaded {
+        distributor: REGISTERED.name.to_string(),
+        release: tag.clone(),
+        name: asset.name.clone(),
+        id,
+      })?;
+    }
+
+    let recorded = journal
+      .lock()
+      .published(REGISTERED.name, tag)
+      .map(|(_, url)| url.to_string());
+
+    if let Some(url) = recorded {
+      info!("The GitLab release has already been created, skipping it");
+      return Ok(vec![url]);
+    }
+
+    let links = release
+      .assets()
+      .map(|asset| {
+        json!({
+          "name": asset.name,
+          "url": self.package_url(version, &asset.name),
+          "link_type": "package",
+        })
+      })
+      .collect::<Vec<_>>();
+
+    let existing = network::retry("Looking up the release", || self.release(&client, tag)).await?;
+
+    let created = match existing {
+      Some(existing) => {
+        let existing = if force {
+          info!("Updating the existing GitLab release for {}", tag);
+
+          network::retry("Updating the release", || async {
+            parse::<Release>(
+              send(
+                self
+                  .request(
+                    &client,
+                    Method::PUT,
+                    &format!("/releases/{}", network::encode(tag)),
+                  )
+                  .json(&json!({
+                    "name": self.payload["name"],
+                    "description": self.payload["description"],
+                  })),
+              )
+              .await?,
+            )
+          })
+          .await?
+        } else {
+          info!(
+            "Reusing the existing GitLab release for {} at {}",
+            tag, existing.links.url
+          );
+
+          existing
+        };
+
+        // link the packages the release doesn't link to yet
+        for link in &links {
+          if existing
+            .assets
+            .links
+            .iter()
+            .any(|existing| link["name"] == existing.name.as_str())
+          {
+            continue;
+          }
+
+          network::retry_create("Linking the package", || async {
+            send(
+              self
+                .request(
+                  &client,
+                  Method::POST,
+                  &format!("/releases/{}/assets/links", network::encode(tag)),
+                )
+                .json(link),
+            )
+            .await
+            .map(|_| ())
+          })
+          .await?;
+        }
+
+        existing
+      }
+      None => {
+        // GitLab creates the tag from the ref if it isn't in the repository yet
+        let tagged = network::retry("Looking up the tag", || self.tag_exists(&client, tag)).await?;
+
+        let mut payload = self.payload.clone();
+        payload["assets"] = json!({ "links": links });
+
+        info!("Creating GitLab release");
+
+        let created = network::retry_create("Creating the release", || async {
+          parse::<Release>(
+            send(
+              self
+                .request(&client, Method::POST, "/releases")
+                .json(&payload),
+            )
+            .await?,
+          )
+        })
+        .await?;
+
+        info!("Created GitLab release at {}", created.links.url);
+
+        journal.lock().created(Created::Distributed {
+          distributor: &REGISTERED,
+          kind: "release",
+          id: tag.clone(),
+          url: created.links.url.clone(),
+          tag: (!tagged).then(|| tag.clone()),
+        });
+
+        created
+      }
+    };
+
+    journal.lock().record(Step::Published {
+      distributor: REGISTERED.name.to_string(),
+      name: tag.clone(),
+      id: tag.clone(),
+      url: created.links.url.clone(),
+    })?;
+
+    Ok(vec![created.links.url])
+  }
+
+  async fn rollback(
+    &self,
+    kind: &str,
+    id: &str,
+    tag: Option<&str>,
+  ) -> Result<(), error::AxolotlError> {
+    let client = network::client()?;
+
+    if kind == "package file" {
+      let (package, file) = id
+        .split_once('/')
+        .and_then(|(package, file)| Some((package.parse().ok()?, file.parse().ok()?)))
+        .ok_or_else(|| {
+          error::AxolotlError::Error(format!("Invalid GitLab package file id {}", id))
+        })?;
+
+      return network::retry("Deleting the package file", || {
+        self.delete_package_file(&client, package, file)
+      })
+      .await;
+    }
+
+    network::retry("Deleting the release", || async {
+      send(self.request(
+        &client,
+        Method::DELETE,
+        &format!("/releases/{}", network::encode(id)),
+      ))
+      .await
+      .map(|_| ())
+    })
+    .await?;
+
+    if let Some(tag) = tag {
+      network::retry("Deleting the tag", || async {
+        send(self.request(
+          &client,
+          Method::DELETE,
+          &format!("/repository/tags/{}", network::encode(tag)),
+        ))
+        .await
+        .map(|_| ())
+      })
+      .await?;
+    }
+
+    Ok(())
+  }
+}
+
+impl GitLabDistributor<'_> {
+  /// A request to the API of the project, authenticated with the token if there is one
+  fn request(&self, client: &reqwest::Client, method: Method, path: &str) -> RequestBuilder {
+    let request = client.request(method, format!("{}{}", self.project_url(), path));
+
+    match &self.token {
+      Some(token) => request.header("PRIVATE-TOKEN", token),
+      None => request,
+    }
+  }
+
+  fn project_url(&self) -> String {
+    format!(
+      "{}/api/v4/projects/{}",
+      self.configuration.url.trim_end_matches('/'),
+      network::encode(&self.configuration.project)
+    )
+  }
+
+  /// Where the file of the package can be downloaded from
+  fn package_url(&self, version: &str, name: &str) -> String {
+    format!(
+      "{}/packages/generic/{}/{}/{}",
+      self.project_url(),
+      network::encode(&self.package),
+      network::encode(version),
+      network::encode(name)
+    )
+  }
+
+  /// The files of the version of the package, if it has been uploaded to before
+  async fn package_files(
+    &self,
+    client: &reqwest::Client,
+    version: &str,
+  ) -> Result<Vec<PackageFile>, error::AxolotlError> {
+    let packages = parse::<Vec<Package>>(
+      send(self.request(client, Method::GET, "/packages").query(&[
+        ("package_type", "generic"),
+        ("package_name", &self.package),
+        ("per_page", "100"),
+      ]))
+      .await?,
+    )?;
+
+    let Some(package) = packages
+      .into_iter()
+      .find(|package| package.version == version)
+    else {
+      return Ok(vec![]);
+    };
+
+    parse(
+      send(
+        self
+          .request(
+            client,
+            Method::GET,
+            &format!("/packages/{}/package_files", package.id),
+          )
+          .query(&[("per_page", "100")]),
+      )
+      .await?,
+    )
+  }
+
+  /// Stream an asset to the package
+  async fn upload(
+    &self,
+    client: &reqwest::Client,
+    version: &str,
+    asset: &Asset,
+    progress: &ProgressBar,
+  ) -> Result<PackageFile, error::AxolotlError> {
+    parse(
+      send(
+        client
+          .put(self.package_url(version, &asset.name))
+          .query(&[("select", "package_file")])
+          .header("PRIVATE-TOKEN", distributor::key(&self.token, &REGISTERED)?)
+          .header(CONTENT_LENGTH, asset.length()?)
+          .body(asset.stream(progress).await?),
+      )
+      .await?,
+    )
+  }
+
+  async fn delete_package_file(
+    &self,
+    client: &reqwest::Client,
+    package: u64,
+    file: u64,
+  ) -> Result<(), error::AxolotlError> {
+    send(self.request(
+      client,
+      Method::DELETE,
+      &format!("/packages/{}/package_files/{}", package, file),
+    ))
+    .await
+    .map(|_| ())
+  }
+
+  /// The release for the tag, if there is one
+  async fn release(
+    &self,
+    client: &reqwest::Client,
+    tag: &str,
+  ) -> Result<Option<Release>, error::AxolotlError> {
+    match send(self.request(
+      client,
+      Method::GET,
+      &format!("/releases/{}", network::encode(tag)),
+    ))
+    .await
+    {
+      Ok(content) => Ok(Some(parse(content)?)),
+      Err(error::AxolotlError::GitLabError { status, .. }) if status == StatusCode::NOT_FOUND => {
+        Ok(None)
+      }
+      Err(error) => Err(error),
+    }
+  }
+
+  /// Whether the tag exists in the repository
+  async fn tag_exists(
+    &self,
+    client: &reqwest::Client,
+    tag: &str,
+  ) -> Result<bool, error::AxolotlError> {
+    match send(self.request(
+      client,
+      Method::GET,
+      &format!("/repository/tags/{}", network::encode(tag)),
+    ))
+    .await
+    {
+      Ok(_) => Ok(true),
+      Err(error::AxolotlError::GitLabError { status, .. }) if status == StatusCode::NOT_FOUND => {
+        Ok(false)
+      }
+      Err(error) => Err(error),
+    }
+  }
+}
+
+async fn send(request: RequestBuilder) -> Result<String, error::AxolotlError> {
+  network::send(request, |status, content| {
+    error::AxolotlError::GitLabError { status, content }
+  })
+  .await
+}
+
+fn parse<T: for<'de> Deserialize<'de>>(content: String) -> Result<T, error::AxolotlError> {
+  Ok(serde_json::from_str(&content)?)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use wiremock::{
+    matchers::{header, method, path},
+    Mock, MockServer, ResponseTemplate,
+  };
+
+  fn configuration(url: &str, project: &str) -> GitLab {
+    GitLab {
+      url: url.to_string(),
+      project: project.to_string(),
+      package: None,
+      reference: None,
+    }
+  }
+
+  fn distributor(configuration: &GitLab) -> GitLabDistributor<'_> {
+    GitLabDistributor {
+      configuration,
+      transformations: None,
+      links: links(configuration),
+      token: Some("token".to_string()),
+      package: "mod".to_string(),
+      payload: serde_json::Value::Null,
+    }
+  }
+
+  #[test]
+  fn links_point_to_the_project_on_the_instance() {
+    let links = links(&configuration("https://gitlab.com/", "group/project")).unwrap();
+
+    assert_eq!(links.host, "https://gitlab.com");
+    assert_eq!(links.repository, "https://gitlab.com/group/project");
+    assert_eq!(links.prefix, "/-");
+  }
+
+  #[test]
+  fn links_are_missing_for_a_project_id() {
+    assert!(links(&configuration("https://gitlab.com", "1234")).is_none());
+  }
+
+  #[test]
+  fn package_url_encodes_every_segment() {
+    let configuration = configuration("https://gitlab.com/", "group/project");
+
+    assert_eq!(
+      distributor(&configuration).package_url("1.0.0+mc1.21", "mod 1.0.0.jar"),
+      "https://gitlab.com/api/v4/projects/group%2Fproject/packages/generic/mod/1.0.0%2Bmc1.21/\
+       mod%201.0.0.jar"
+    );
+  }
+
+  #[tokio::test]
+  async fn release_is_missing_for_unknown_tags() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+      .and(path(
+        "/api/v4/projects/group%2Fproject/releases/mc1.21%2F1.0.0",
+      ))
+      .and(header("private-token", "token"))
+      .respond_with(ResponseTemplate::new(404))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let configuration = configuration(&server.uri(), "group/project");
+    let client = network::client().unwrap();
+
+    assert!(distributor(&configuration)
+      .release(&client, "mc1.21/1.0.0")
+      .await
+      .unwrap()
+      .is_none());
+  }
+
+  #[tokio::test]
+  async fn tag_exists_looks_up_the_tag() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+      .and(path(
+        "/api/v4/projects/group%2Fproject/repository/tags/v1.0.0",
+      ))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+      .mount(&server)
+      .await;
+    Mock::given(method("GET"))
+      .and(path(
+        "/api/v4/projects/group%2Fproject/repository/tags/v2.0.0",
+      ))
+      .respond_with(ResponseTemplate::new(404))
+      .mount(&server)
+      .await;
+
+    let configuration = configuration(&server.uri(), "group/project");
+    let distributor = distributor(&configuration);
+    let client = network::client().unwrap();
+
+    assert!(distributor.tag_exists(&client, "v1.0.0").await.unwrap());
+    assert!(!distributor.tag_exists(&client, "v2.0.0").await.unwrap());
+  }
+
+  #[tokio::test]
+  async fn rollback_deletes_package_files() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+      .and(path(
+        "/api/v4/projects/group%2Fproject/packages/3/package_files/4",
+      ))
+      .respond_with(ResponseTemplate::new(204))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let configuration = configuration(&server.uri(), "group/project");
+    let distributor = distributor(&configuration);
+
+    distributor
+      .rollback("package file", "3/4", None)
+      .await
+      .unwrap();
+    assert!(distributor
+      .rollback("package file", "3", None)
+      .await
+      .is_err());
+  }
+
+  #[tokio::test]
+  async fn rollback_deletes_the_release_and_its_tag() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+      .and(path(
+        "/api/v4/projects/group%2Fproject/releases/mc1.21%2F1.0.0",
+      ))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+      .expect(1)
+      .mount(&server)
+      .await;
+    Mock::given(method("DELETE"))
+      .and(path(
+        "/api/v4/projects/group%2Fproject/repository/tags/mc1.21%2F1.0.0",
+      ))
+      .respond_with(ResponseTemplate::new(204))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let configuration = configuration(&server.uri(), "group/project");
+
+    distributor(&configuration)
+      .rollback("release", "mc1.21/1.0.0", Some("mc1.21/1.0.0"))
+      .await
+      .unwrap();
+  }
+}
@@ -0,0 +1,40 @@
+use crate::{distributor, error, journal::Journal};
+
+/// Print the finished publishes, newest first, along with the unfinished one if there is one
+pub fn history() -> Result<(), error::AxolotlError> {
+  if let Some(journal) = Journal::read()? {
+    warn!(
+      "The publish of {} started {} is unfinished - run `axolotl mod publish --resume` to continue it",
+      journal.tag, journal.started
+    );
+  }
+
+  let history = Journal::history()?;
+
+  if history.is_empty() {
+    info!("Nothing has been published yet");
+  }
+
+  for journal in history.iter().rev() {
+    let mut lines = vec![format!(
+      "{} ({}, {}) published {}",
+      journal.tag,
+      journal.version,
+      journal.version_type.to_string().to_lowercase(),
+      journal.finished.as_deref().unwrap_or(&journal.started)
+    )];
+
+    if let Some(commit) = &journal.commit {
+      lines.push(format!("  commit {}", commit));
+    }
+
+    for (name, url) in journal.urls() {
+      let title = distributor::find(name).map_or(name, |registered| registered.title);
+      lines.push(format!("  {}: {}", title, url));
+    }
+
+    info!("{}", lines.join("\n"));
+  }
+
+  Ok(())
+}
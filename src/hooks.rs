@@ -0,0 +1,46 @@
+use crate::error;
+use std::process::Command;
+
+/// Run each command through the shell in order, stopping at the first one that fails
+pub fn run(
+  stage: &str,
+  commands: &[String],
+  environment: &[(String, String)],
+  dry_run: bool,
+) -> Result<(), error::AxolotlError> {
+  for command in commands {
+    if dry_run {
+      info!("Would run {} hook `{}`", stage, command);
+      continue;
+    }
+
+    info!("Running {} hook `{}`", stage, command);
+
+    let status = shell(command)
+      .envs(environment.iter().map(|(key, value)| (key, value)))
+      .status()?;
+
+    if !status.success() {
+      return Err(error::AxolotlError::Error(format!(
+        "The {} hook `{}` failed ({})",
+        stage, command, status
+      )));
+    }
+  }
+
+  Ok(())
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+  let mut shell = Command::new("cmd");
+  shell.args(["/C", command]);
+  shell
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+  let mut shell = Command::new("sh");
+  shell.args(["-c", command]);
+  shell
+}
@@ -0,0 +1,417 @@
+use crate::{constants, error, rollback::Created, util::VersionType};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::{
+  fs,
+  path::Path,
+  sync::{Mutex, MutexGuard, PoisonError},
+};
+
+/// A completed step of a publish
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum Step {
+  PrePublishHooks,
+  /// A release or version published by a distributor, told apart from the others it published by
+  /// its name
+  Published {
+    distributor: String,
+    name: String,
+    id: String,
+    url: String,
+  },
+  /// A version that was created but not scheduled yet, which is scheduled once it is reused
+  Unscheduled {
+    distributor: String,
+    name: String,
+    id: String,
+  },
+  /// A file uploaded to a release, for distributors that upload files one at a time
+  Uploaded {
+    distributor: String,
+    release: String,
+    name: String,
+    id: String,
+  },
+}
+
+/// The progress of a publish, saved after every step so that it can be resumed, and kept in the
+/// history once it is finished
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Journal {
+  pub version: String,
+  pub version_type: VersionType,
+  pub tag: String,
+  /// The changelog before the template is applied
+  pub changelog: Option<String>,
+  pub commit: Option<String>,
+  pub started: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub finished: Option<String>,
+  pub steps: Vec<Step>,
+  /// Dry runs are never saved
+  #[serde(skip)]
+  dry_run: bool,
+  /// What this run created, as opposed to reused or resumed
+  #[serde(skip)]
+  created: Vec<Created>,
+}
+
+impl Journal {
+  pub fn new(
+    version: String,
+    version_type: VersionType,
+    tag: String,
+    changelog: Option<String>,
+    commit: Option<String>,
+    dry_run: bool,
+  ) -> Self {
+    Journal {
+      version,
+      version_type,
+      tag,
+      changelog,
+      commit,
+      started: Local::now().to_rfc3339(),
+      finished: None,
+      steps: vec![],
+      dry_run,
+      created: vec![],
+    }
+  }
+
+  /// The journal of the unfinished publish, if there is one
+  pub fn read() -> Result<Option<Self>, error::AxolotlError> {
+    if !Path::new(constants::STATE).exists() {
+      return Ok(None);
+    }
+
+    Ok(Some(serde_json::from_str(&fs::read_to_string(
+      constants::STATE,
+    )?)?))
+  }
+
+  pub fn save(&self) -> Result<(), error::AxolotlError> {
+    if self.dry_run {
+      return Ok(());
+    }
+
+    fs::create_dir_all(constants::STATE_FOLDER)?;
+    Ok(fs::write(
+      constants::STATE,
+      serde_json::to_string_pretty(self)?,
+    )?)
+  }
+
+  pub fn record(&mut self, step: Step) -> Result<(), error::AxolotlError> {
+    self.steps.push(step);
+    self.save()
+  }
+
+  pub fn created(&mut self, created: Created) {
+    self.created.push(created);
+  }
+
+  pub fn take_created(&mut self) -> Vec<Created> {
+    std::mem::take(&mut self.created)
+  }
+
+  /// Drop the steps that refer to exactly what has been deleted
+  pub fn forget(&mut self, created: &Created) {
+    let Created::Distributed {
+      distributor, id, ..
+    } = created
+    else {
+      return;
+    };
+
+    self.steps.retain(|step| match step {
+      Step::Published {
+        distributor: recorded,
+        id: published,
+        ..
+      } => recorded != distributor.name || published != id,
+      Step::Unscheduled {
+        distributor: recorded,
+        id: created,
+        ..
+      } => recorded != distributor.name || created != id,
+      // files are matched by their own id only, as what they were uploaded to (such as a Maven
+      // version) is rolled back before them; the files of a deleted release stay recorded under
+      // its id, which a new release doesn't get
+      Step::Uploaded {
+        distributor: recorded,
+        id: uploaded,
+        ..
+      } => recorded != distributor.name || uploaded != id,
+      _ => true,
+    });
+  }
+
+  pub fn completed(&self, step: &Step) -> bool {
+    self.steps.contains(step)
+  }
+
+  /// The id and URL of what the distributor published under the name, if it has been published
+  pub fn published(&self, distributor: &str, name: &str) -> Option<(&str, &str)> {
+    self.steps.iter().find_map(|step| match step {
+      Step::Published {
+        distributor: recorded,
+        name: published,
+        id,
+        url,
+      } if recorded == distributor && published == name => Some((id.as_str(), url.as_str())),
+      _ => None,
+    })
+  }
+
+  /// Whether the distributor created a version under the name without scheduling it
+  pub fn unscheduled(&self, distributor: &str, name: &str) -> bool {
+    self.steps.iter().any(|step| {
+      matches!(
+        step,
+        Step::Unscheduled { distributor: recorded, name: created, .. }
+          if recorded == distributor && created == name
+      )
+    })
+  }
+
+  pub fn uploaded(&self, distributor: &str, release: &str, name: &str) -> bool {
+    self.steps.iter().any(|step| {
+      matches!(
+        step,
+        Step::Uploaded { distributor: recorded, release: uploaded_to, name: uploaded, .. }
+          if recorded == distributor && uploaded_to == release && uploaded == name
+      )
+    })
+  }
+
+  /// The URLs of everything published, along with the name of the distributor
+  pub fn urls(&self) -> Vec<(&str, &str)> {
+    self
+      .steps
+      .iter()
+      .filter_map(|step| match step {
+        Step::Published {
+          distributor, url, ..
+        } => Some((distributor.as_str(), url.as_str())),
+        _ => None,
+      })
+      .collect()
+  }
+
+  /// Move the journal from the state file to the end of the history
+  pub fn finish(mut self) -> Result<(), error::AxolotlError> {
+    if self.dry_run {
+      return Ok(());
+    }
+
+    self.finished = Some(Local::now().to_rfc3339());
+
+    let mut history = Journal::history()?;
+    history.push(self);
+
+    fs::create_dir_all(constants::STATE_FOLDER)?;
+    fs::write(constants::HISTORY, serde_json::to_string_pretty(&history)?)?;
+
+    if Path::new(constants::STATE).exists() {
+      fs::remove_file(constants::STATE)?;
+    }
+
+    Ok(())
+  }
+
+  /// The journals of every finished publish, oldest first
+  pub fn history() -> Result<Vec<Self>, error::AxolotlError> {
+    if !Path::new(constants::HISTORY).exists() {
+      return Ok(vec![]);
+    }
+
+    Ok(serde_json::from_str(&fs::read_to_string(
+      constants::HISTORY,
+    )?)?)
+  }
+}
+
+/// A journal shared by the distributors running at the same time
+pub struct Shared<'a>(Mutex<&'a mut Journal>);
+
+impl<'a> Shared<'a> {
+  pub fn new(journal: &'a mut Journal) -> Self {
+    Shared(Mutex::new(journal))
+  }
+
+  /// The guard must not be held across an await, or the other distributor can't record its steps
+  pub fn lock(&self) -> MutexGuard<'_, &'a mut Journal> {
+    // a panic while recording a step leaves the journal as it was
+    self.0.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{maven, modrinth};
+
+  /// A journal for a dry run, which is never saved
+  fn journal() -> Journal {
+    Journal::new(
+      "1.0.0".to_string(),
+      VersionType::Release,
+      "v1.0.0".to_string(),
+      None,
+      None,
+      true,
+    )
+  }
+
+  fn unscheduled(id: &str) -> Step {
+    Step::Unscheduled {
+      distributor: "modrinth".to_string(),
+      name: "v1.0.0".to_string(),
+      id: id.to_string(),
+    }
+  }
+
+  fn version(id: &str) -> Created {
+    Created::Distributed {
+      distributor: &modrinth::REGISTERED,
+      kind: "version",
+      id: id.to_string(),
+      url: String::new(),
+      tag: None,
+    }
+  }
+
+  #[test]
+  fn unscheduled_versions_are_found_by_name() {
+    let mut journal = journal();
+    journal.record(unscheduled("abc")).unwrap();
+
+    assert!(journal.unscheduled("modrinth", "v1.0.0"));
+    assert!(!journal.unscheduled("modrinth", "v1.0.0 (fabric)"));
+    assert!(!journal.unscheduled("github", "v1.0.0"));
+  }
+
+  #[test]
+  fn forget_drops_unscheduled_versions_that_were_deleted() {
+    let mut journal = journal();
+    journal.record(unscheduled("abc")).unwrap();
+    journal.record(unscheduled("def")).unwrap();
+    journal.forget(&version("abc"));
+
+    assert_eq!(journal.steps, [unscheduled("def")]);
+  }
+
+  fn uploaded(release: &str, id: &str) -> Step {
+    Step::Uploaded {
+      distributor: "maven".to_string(),
+      release: release.to_string(),
+      name: id.rsplit('/').next().unwrap_or_default().to_string(),
+      id: id.to_string(),
+    }
+  }
+
+  fn deployed(kind: &'static str, id: &str) -> Created {
+    Created::Distributed {
+      distributor: &maven::REGISTERED,
+      kind,
+      id: id.to_string(),
+      url: String::new(),
+      tag: None,
+    }
+  }
+
+  #[test]
+  fn forget_keeps_the_files_of_a_forgotten_version() {
+    let path = "com/example/mod/1.0.0";
+    let jar = format!("{}/mod-1.0.0.jar", path);
+    let pom = format!("{}/mod-1.0.0.pom", path);
+    let mut journal = journal();
+    journal.record(uploaded(path, &jar)).unwrap();
+    journal.record(uploaded(path, &pom)).unwrap();
+
+    journal.forget(&deployed("version", path));
+    assert_eq!(journal.steps, [uploaded(path, &jar), uploaded(path, &pom)]);
+
+    journal.forget(&deployed("file", &jar));
+    assert_eq!(journal.steps, [uploaded(path, &pom)]);
+  }
+
+  fn published(distributor: &str, name: &str, id: &str) -> Step {
+    Step::Published {
+      distributor: distributor.to_string(),
+      name: name.to_string(),
+      id: id.to_string(),
+      url: format!("https://example.com/{}", id),
+    }
+  }
+
+  #[test]
+  fn published_steps_are_found_by_distributor_and_name() {
+    let mut journal = journal();
+    journal.record(published("github", "v1.0.0", "1")).unwrap();
+    journal
+      .record(published("modrinth", "v1.0.0 (fabric)", "abc"))
+      .unwrap();
+
+    assert_eq!(
+      journal.published("modrinth", "v1.0.0 (fabric)"),
+      Some(("abc", "https://example.com/abc"))
+    );
+    assert_eq!(journal.published("modrinth", "v1.0.0"), None);
+    assert_eq!(journal.published("forgejo", "v1.0.0"), None);
+    assert_eq!(
+      journal.urls(),
+      [
+        ("github", "https://example.com/1"),
+        ("modrinth", "https://example.com/abc")
+      ]
+    );
+  }
+
+  #[test]
+  fn uploaded_files_are_found_by_release_and_name() {
+    let mut journal = journal();
+    journal
+      .record(Step::Uploaded {
+        distributor: "github".to_string(),
+        release: "1".to_string(),
+        name: "mod-1.0.0.jar".to_string(),
+        id: "2".to_string(),
+      })
+      .unwrap();
+
+    assert!(journal.uploaded("github", "1", "mod-1.0.0.jar"));
+    assert!(!journal.uploaded("github", "3", "mod-1.0.0.jar"));
+    assert!(!journal.uploaded("forgejo", "1", "mod-1.0.0.jar"));
+    assert!(!journal.uploaded("github", "1", "mod-1.0.0-sources.jar"));
+  }
+
+  #[test]
+  fn resumed_journals_keep_their_steps() {
+    let mut journal = journal();
+    journal.record(Step::PrePublishHooks).unwrap();
+    journal.record(published("github", "v1.0.0", "1")).unwrap();
+
+    let resumed: Journal = serde_json::from_str(&serde_json::to_string(&journal).unwrap()).unwrap();
+
+    assert!(resumed.completed(&Step::PrePublishHooks));
+    assert_eq!(resumed.steps, journal.steps);
+    assert_eq!(resumed.tag, "v1.0.0");
+    assert_eq!(resumed.version_type, VersionType::Release);
+    assert!(resumed.finished.is_none());
+  }
+
+  #[test]
+  fn steps_are_saved_by_name() {
+    assert_eq!(
+      serde_json::to_value(Step::PrePublishHooks).unwrap(),
+      serde_json::json!({ "step": "pre_publish_hooks" })
+    );
+    assert_eq!(
+      serde_json::to_value(published("github", "v1.0.0", "1")).unwrap()["step"],
+      "published"
+    );
+  }
+}
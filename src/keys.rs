@@ -1,17 +1,19 @@
-use crate::{constants, error, file::ToRead};
+use crate::{constants, distributor, error, file::ToRead};
 use age::{secrecy::Secret, Decryptor, Encryptor};
 use base64::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::{
-  fs,
+  collections::BTreeMap,
+  env, fs,
   io::{Read, Write},
 };
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Keys {
   pub encrypted: bool,
-  pub modrinth: Option<String>,
-  pub github: Option<String>,
+  /// The token of each distributor, by its name in the registry
+  #[serde(flatten)]
+  pub tokens: BTreeMap<String, String>,
 }
 
 impl Keys {
@@ -29,7 +31,7 @@ impl Keys {
 
   pub fn read_raw() -> Result<Keys, error::AxolotlError> {
     let file = ToRead::new(constants::KEYS.as_path())?;
-    return Ok(toml::from_str(file.read_to_string()?.as_str())?);
+    Ok(toml::from_str(file.read_to_string()?.as_str())?)
   }
 
   pub fn write(keys: Keys) -> Result<(), error::AxolotlError> {
@@ -39,6 +41,22 @@ impl Keys {
     )?)
   }
 
+  /// Override stored tokens with the ones provided through the environment (as
+  /// `AXOLOTL_<NAME>_TOKEN`), if any
+  pub fn with_environment(mut self) -> Keys {
+    for registered in distributor::REGISTRY {
+      if let Ok(token) = env::var(format!("AXOLOTL_{}_TOKEN", registered.name.to_uppercase())) {
+        self.tokens.insert(registered.name.to_string(), token);
+      }
+    }
+
+    self
+  }
+
+  pub fn token(&self, name: &str) -> Option<String> {
+    self.tokens.get(name).cloned()
+  }
+
   pub fn encrypt(plaintext: String, key: String) -> Result<String, error::AxolotlError> {
     let encryptor = Encryptor::with_user_passphrase(Secret::new(key));
 
@@ -67,38 +85,22 @@ impl Keys {
   pub fn encrypted(&self, key: String) -> Result<Keys, error::AxolotlError> {
     Ok(Keys {
       encrypted: true,
-      modrinth: match self.modrinth.clone() {
-        Some(token) => Some(Self::encrypt(token, key.clone())?),
-        None => None,
-      },
-      github: match self.github.clone() {
-        Some(token) => Some(Self::encrypt(token, key)?),
-        None => None,
-      },
+      tokens: self
+        .tokens
+        .iter()
+        .map(|(name, token)| Ok((name.clone(), Self::encrypt(token.clone(), key.clone())?)))
+        .collect::<Result<_, error::AxolotlError>>()?,
     })
   }
 
   pub fn decrypted(&self, key: String) -> Result<Keys, error::AxolotlError> {
     Ok(Keys {
       encrypted: false,
-      modrinth: match self.modrinth.clone() {
-        Some(token) => Some(Self::decrypt(token, key.clone())?),
-        None => None,
-      },
-      github: match self.github.clone() {
-        Some(token) => Some(Self::decrypt(token, key)?),
-        None => None,
-      },
+      tokens: self
+        .tokens
+        .iter()
+        .map(|(name, token)| Ok((name.clone(), Self::decrypt(token.clone(), key.clone())?)))
+        .collect::<Result<_, error::AxolotlError>>()?,
     })
   }
 }
-
-impl Default for Keys {
-  fn default() -> Self {
-    Keys {
-      encrypted: false,
-      modrinth: None,
-      github: None,
-    }
-  }
-}
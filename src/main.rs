@@ -3,27 +3,41 @@ extern crate pretty_env_logger;
 #[macro_use]
 extern crate log;
 
+mod changelog;
+mod check;
 mod configuration;
 mod constants;
+mod distributor;
 mod error;
 mod file;
+mod forge;
+mod forgejo;
+mod git;
+mod github;
+mod gitlab;
+mod history;
+mod hooks;
+mod journal;
 mod keys;
+mod maven;
+mod metadata;
 mod modrinth;
+mod network;
+mod pattern;
+mod progress;
+mod publish;
+mod release;
+mod rollback;
+mod template;
 mod util;
 
-use clap::{Parser, Subcommand, ValueEnum};
-use configuration::{Changelog, Configuration, GitHub, Modrinth, ModrinthDependency};
-use file::ToRead;
-use inquire::{Confirm, Editor, Select, Text};
+use clap::{Args, Parser, Subcommand};
+use configuration::{Configuration, ModrinthStatus};
+use distributor::Registered;
+use indicatif_log_bridge::LogWrapper;
+use inquire::{Confirm, Text};
 use keys::Keys;
-use modrinth_api::{
-  apis,
-  models::{self, CreatableVersion, VersionDependency},
-};
-use octocrab::Octocrab;
-use reqwest::{multipart::Part, Body};
-use std::{env, process};
-use tokio_util::codec::{BytesCodec, FramedRead};
+use std::{env, path::PathBuf, process};
 use util::{get_keys, VersionType};
 
 #[derive(Parser, Debug)]
@@ -58,7 +72,81 @@ enum ModCommands {
 
   /// Publish the mod
   #[clap(visible_alias = "p")]
-  Publish,
+  Publish(PublishArguments),
+
+  /// Resolve Modrinth slugs in the configuration file to project ids
+  #[clap(visible_alias = "c")]
+  Check(CheckArguments),
+
+  /// Release Modrinth drafts, moving them to their requested status
+  #[clap(visible_alias = "r")]
+  Release(ReleaseArguments),
+
+  /// List the finished publishes
+  #[clap(visible_alias = "h")]
+  History,
+}
+
+#[derive(Args, Debug)]
+struct PublishArguments {
+  /// The version to publish (prompted for if absent)
+  #[arg(long = "version", env = "AXOLOTL_VERSION")]
+  version: Option<String>,
+
+  /// The version type (prompted for if absent)
+  #[arg(long, value_enum, env = "AXOLOTL_VERSION_TYPE")]
+  version_type: Option<VersionType>,
+
+  /// Read the changelog from this file instead of using the configured source
+  #[arg(long, env = "AXOLOTL_CHANGELOG_FILE")]
+  changelog_file: Option<PathBuf>,
+
+  /// Skip the confirmation prompt
+  #[arg(short, long, env = "AXOLOTL_YES")]
+  yes: bool,
+
+  /// Update releases and versions that already exist, replacing their files
+  #[arg(long, env = "AXOLOTL_FORCE")]
+  force: bool,
+
+  /// Continue the unfinished publish instead of starting over
+  #[arg(long, env = "AXOLOTL_RESUME")]
+  resume: bool,
+
+  /// Delete the tag, release and versions this run created if distributing fails
+  #[arg(long, env = "AXOLOTL_ATOMIC")]
+  atomic: bool,
+
+  /// The status of the Modrinth versions (overrides the configured one)
+  #[arg(long, value_enum, env = "AXOLOTL_MODRINTH_STATUS")]
+  modrinth_status: Option<ModrinthStatus>,
+
+  /// When scheduled Modrinth versions are released, as an RFC 3339 timestamp (overrides the
+  /// configured one)
+  #[arg(long, env = "AXOLOTL_MODRINTH_RELEASE_AT")]
+  modrinth_release_at: Option<String>,
+
+  /// Resolve everything and print what would be published without creating anything
+  #[arg(long, env = "AXOLOTL_DRY_RUN")]
+  dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+struct CheckArguments {
+  /// Rewrite the configuration file without asking
+  #[arg(short, long, env = "AXOLOTL_YES")]
+  yes: bool,
+}
+
+#[derive(Args, Debug)]
+struct ReleaseArguments {
+  /// Only release drafts with this version number
+  #[arg(long = "version", env = "AXOLOTL_VERSION")]
+  version: Option<String>,
+
+  /// Print the drafts that would be released without changing anything
+  #[arg(long, env = "AXOLOTL_DRY_RUN")]
+  dry_run: bool,
 }
 
 #[derive(Subcommand, Debug)]
@@ -77,15 +165,15 @@ enum KeyCommands {
   /// Set a key
   #[clap(visible_alias = "s")]
   Set {
-    #[arg(value_enum)]
-    distributor: Distributor,
+    #[arg(value_parser = distributor::parser())]
+    distributor: &'static Registered,
   },
 
   /// Remove a key
   #[clap(visible_alias = "r")]
   Remove {
-    #[arg(value_enum)]
-    distributor: Distributor,
+    #[arg(value_parser = distributor::parser())]
+    distributor: &'static Registered,
   },
 }
 
@@ -100,18 +188,6 @@ enum EncryptionCommands {
   Disable,
 }
 
-#[derive(Debug, Clone, ValueEnum)]
-#[clap(rename_all = "lower")]
-enum Distributor {
-  /// [aliases: m]
-  #[clap(alias = "m")]
-  Modrinth,
-
-  /// [aliases: g]
-  #[clap(alias = "g")]
-  GitHub,
-}
-
 #[tokio::main]
 async fn main() {
   match run().await {
@@ -126,7 +202,15 @@ async fn main() {
 async fn run() -> Result<(), error::AxolotlError> {
   env::set_var("RUST_LOG", "info");
 
-  pretty_env_logger::init();
+  let logger = pretty_env_logger::formatted_builder()
+    .parse_filters(&env::var("RUST_LOG").unwrap_or_default())
+    .build();
+  let level = logger.filter();
+
+  LogWrapper::new(progress::PROGRESS.clone(), logger)
+    .try_init()
+    .map_err(|error| error::AxolotlError::Error(error.to_string()))?;
+  log::set_max_level(level);
   Keys::initialize()?;
 
   let arguments = Arguments::parse();
@@ -148,134 +232,14 @@ async fn run() -> Result<(), error::AxolotlError> {
         Configuration::write(Configuration::default())?;
 
         info!(
-          "The configuration file has been written to {}",
+          "The configuration file has been written to {} - once it is filled in, run `axolotl mod check` to resolve Modrinth slugs to ids",
           constants::CONFIGURATION
         );
       }
-      ModCommands::Publish => {
-        let configuration = Configuration::read()?;
-        let (keys, _) = get_keys()?;
-
-        let changelog = match configuration.changelog {
-          Some(changelog) => match changelog {
-            Changelog::File { file } => {
-              let file = ToRead::new(file)?;
-              Some(file.read_to_string()?)
-            }
-            Changelog::Editor => Some(Editor::new("Write the changelog").prompt()?),
-          },
-          None => None,
-        };
-
-        let version = Text::new("Version").prompt()?;
-        let tag = format!("v{}", version);
-
-        let version_type = Select::new(
-          "Version type",
-          vec![VersionType::Release, VersionType::Beta, VersionType::Alpha],
-        )
-        .prompt()?;
-
-        let asset_name = configuration
-          .artifact
-          .pattern
-          .replace(constants::VERSION_REPLACE, version.as_str());
-        let artifact = ToRead::new(configuration.artifact.folder.join(asset_name.clone()))?;
-
-        if let Some(GitHub {
-          repo: (user, repo),
-          draft,
-        }) = configuration.github
-        {
-          if let Some(token) = keys.github {
-            let octocrab = Octocrab::builder().personal_token(token).build()?;
-            let repo = octocrab.repos(user, repo);
-            let releases = repo.releases();
-
-            info!("Creating release");
-
-            let release = releases
-              .create(&tag.clone())
-              .name(&tag.clone())
-              .body(&changelog.clone().unwrap_or_default())
-              .draft(draft)
-              .prerelease(version_type != VersionType::Release)
-              .send()
-              .await?;
-
-            info!(
-              "Created release{} at {}",
-              if draft { " draft" } else { "" },
-              release.html_url
-            );
-
-            info!("Uploading artifact");
-
-            let asset = releases
-              .upload_asset(release.id.0, &asset_name, artifact.read()?.into())
-              .label(&asset_name)
-              .send()
-              .await?;
-
-            info!("Uploaded artifact to {}", asset.browser_download_url);
-          } else {
-            error!("A GitHub token was not provided, skipping distributing to GitHub Releases");
-          }
-        }
-
-        if let Some(Modrinth {
-          id,
-          featured,
-          dependencies,
-        }) = configuration.modrinth
-        {
-          if let Some(token) = keys.modrinth {
-            let config = apis::configuration::Configuration::with_api_key(token)?;
-
-            apis::projects_api::check_project_validity(&config, &id).await?;
-
-            // TODO
-            let static_asset_name: &'static str = Box::leak(asset_name.clone().into_boxed_str());
-
-            info!("Uploading version");
-
-            let version = modrinth::create_version(
-              &config,
-              CreatableVersion {
-                name: tag,
-                version_number: version,
-                changelog: Some(changelog),
-                dependencies: dependencies
-                  .iter()
-                  .map(|d| <ModrinthDependency as Into<VersionDependency>>::into(d.clone()))
-                  .collect(),
-                game_versions: configuration.artifact.game_versions,
-                version_type: version_type.into(),
-                loaders: configuration.artifact.loaders,
-                featured,
-                status: Some(models::creatable_version::Status::Listed),
-                requested_status: None,
-                project_id: id,
-                file_parts: vec![asset_name.clone()],
-                primary_file: Some(asset_name),
-              },
-              Part::stream(Body::wrap_stream(FramedRead::new(
-                artifact.open().await?,
-                BytesCodec::new(),
-              )))
-              .file_name(static_asset_name),
-            )
-            .await?;
-
-            info!(
-              "Uploaded version to https://modrinth.com/project/{}/version/{}",
-              version.project_id, version.id
-            )
-          } else {
-            error!("A Modrinth token was not provided, skipping distributing to Modrinth");
-          }
-        }
-      }
+      ModCommands::Check(arguments) => check::check(arguments).await?,
+      ModCommands::Publish(arguments) => publish::publish(arguments).await?,
+      ModCommands::Release(arguments) => release::release(arguments).await?,
+      ModCommands::History => history::history()?,
     },
     Commands::Keys { command } => {
       let raw = Keys::read_raw()?;
@@ -316,10 +280,7 @@ async fn run() -> Result<(), error::AxolotlError> {
           let (mut keys, key) = get_keys()?;
           let value = Text::new("Enter the new key").prompt()?;
 
-          match distributor {
-            Distributor::Modrinth => keys.modrinth = Some(value),
-            Distributor::GitHub => keys.github = Some(value),
-          }
+          keys.tokens.insert(distributor.name.to_string(), value);
 
           if let Some(key) = key {
             keys = keys.encrypted(key)?;
@@ -332,10 +293,7 @@ async fn run() -> Result<(), error::AxolotlError> {
         KeyCommands::Remove { distributor } => {
           let (mut keys, key) = get_keys()?;
 
-          match distributor {
-            Distributor::Modrinth => keys.modrinth = None,
-            Distributor::GitHub => keys.github = None,
-          }
+          keys.tokens.remove(distributor.name);
 
           if let Some(key) = key {
             keys = keys.encrypted(key)?;
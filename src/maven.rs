@@ -0,0 +1,825 @@
+use crate::{
+  configuration::{Configuration, Maven},
+  distributor::{self, Context, Distributor, Registered},
+  error,
+  journal::Step,
+  keys::Keys,
+  network, progress,
+  publish::{Asset, Target},
+  rollback::Created,
+  util::VersionType,
+  PublishArguments,
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use indicatif::ProgressBar;
+use reqwest::{header::CONTENT_LENGTH, Method, RequestBuilder, StatusCode, Url};
+use sha2::Digest;
+use std::{
+  fs,
+  io::{self, Read},
+  path::{Path, PathBuf},
+};
+
+pub static REGISTERED: Registered = Registered {
+  name: "maven",
+  alias: "mv",
+  title: "Maven",
+  create,
+};
+
+fn create<'a>(
+  configuration: &'a Configuration,
+  keys: &Keys,
+  dry_run: bool,
+) -> Option<Box<dyn Distributor + 'a>> {
+  let maven = configuration.maven.as_ref()?;
+
+  // a folder is written to directly, so it needs no key
+  let token = if maven.url.starts_with("file:") {
+    keys.token(REGISTERED.name)
+  } else {
+    distributor::token(keys, &REGISTERED, dry_run)?
+  };
+
+  Some(Box::new(MavenDistributor {
+    configuration: maven,
+    token,
+    folder: None,
+  }))
+}
+
+/// Deploys the artifact of every target to the `[maven]` repository, along with its POM, its
+/// sources and their checksums, and adds the version to the metadata
+pub struct MavenDistributor<'a> {
+  configuration: &'a Maven,
+  token: Option<String>,
+  /// The folder of a `file://` repository, found by `validate`
+  folder: Option<PathBuf>,
+}
+
+/// The content of a file that is deployed
+enum Content<'a> {
+  Asset(&'a Asset),
+  Generated(String),
+}
+
+/// The checksums Maven expects next to every file, by extension
+const CHECKSUMS: [&str; 4] = ["md5", "sha1", "sha256", "sha512"];
+
+#[async_trait]
+impl Distributor for MavenDistributor<'_> {
+  fn registered(&self) -> &'static Registered {
+    &REGISTERED
+  }
+
+  fn validate(&mut self, _arguments: &PublishArguments) -> Result<(), error::AxolotlError> {
+    let url = Url::parse(&self.configuration.url).map_err(|error| {
+      error::AxolotlError::Error(format!(
+        "Invalid Maven repository URL {}: {}",
+        self.configuration.url, error
+      ))
+    })?;
+
+    match url.scheme() {
+      "http" | "https" => {}
+      "file" => {
+        self.folder = Some(url.to_file_path().map_err(|_| {
+          error::AxolotlError::Error(format!(
+            "Invalid Maven repository folder {}",
+            self.configuration.url
+          ))
+        })?);
+      }
+      scheme => {
+        return Err(error::AxolotlError::Error(format!(
+          "Unsupported Maven repository URL {} - use http, https or file",
+          scheme
+        )))
+      }
+    }
+
+    Ok(())
+  }
+
+  async fn publish(&self, context: &Context<'_, '_>) -> Result<Vec<String>, error::AxolotlError> {
+    let Context {
+      release,
+      journal,
+      force,
+      dry_run,
+      ..
+    } = *context;
+    let Maven { url, group_id, .. } = self.configuration;
+    let version = &release.version;
+
+    if dry_run {
+      for target in &release.targets {
+        let artifact_id = self.artifact_id(target);
+
+        info!(
+          "Would deploy {} to {} as {}:{}:{}\n{}",
+          files(target, group_id, &artifact_id, version)
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+          url,
+          group_id,
+          artifact_id,
+          version,
+          pom(group_id, &artifact_id, version, &target.artifact.name)
+        );
+      }
+
+      return Ok(vec![]);
+    }
+
+    let client = network::client()?;
+    let mut urls = vec![];
+
+    for target in &release.targets {
+      let artifact_id = self.artifact_id(target);
+      let directory = format!("{}/{}", group_id.replace('.', "/"), artifact_id);
+      let path = format!("{}/{}", directory, version);
+      let location = self.location(&path);
+
+      let recorded = journal
+        .lock()
+        .published(REGISTERED.name, &artifact_id)
+        .is_some();
+
+      if recorded {
+        info!(
+          "{}:{} has already been deployed, skipping it",
+          artifact_id, version
+        );
+        urls.push(location);
+        continue;
+      }
+
+      for (name, content) in files(target, group_id, &artifact_id, version) {
+        let file = format!("{}/{}", path, name);
+
+        if journal.lock().uploaded(REGISTERED.name, &path, &name) {
+          info!("{} has already been uploaded, skipping it", name);
+          continue;
+        }
+
+        let existed = network::retry("Looking up the file", || self.exists(&client, &file)).await?;
+
+        if existed {
+          if !force {
+            info!("{} has already been uploaded, skipping it", name);
+            continue;
+          }
+
+          info!("Replacing {}", name);
+        }
+
+        let checksums = match &content {
+          Content::Asset(asset) => {
+            let bar = asset.progress(REGISTERED.title)?;
+            let uploaded = network::retry(&format!("Uploading {}", name), || {
+              self.upload(&client, &file, asset, &bar)
+            })
+            .await;
+
+            progress::finish(&bar, &uploaded);
+            uploaded?;
+
+            checksums(fs::File::open(asset.file.path())?)?
+          }
+          Content::Generated(generated) => {
+            let bytes = generated.clone().into_bytes();
+
+            network::retry(&format!("Uploading {}", name), || {
+              self.put(&client, &file, bytes.clone())
+            })
+            .await?;
+
+            checksums(bytes.as_slice())?
+          }
+        };
+
+        self.put_checksums(&client, &file, checksums).await?;
+
+        info!("Uploaded {} to {}", name, self.location(&file));
+
+        if !existed {
+          journal.lock().created(Created::Distributed {
+            distributor: &REGISTERED,
+            kind: "file",
+            id: file.clone(),
+            url: self.location(&file),
+            tag: None,
+          });
+        }
+
+        journal.lock().record(Step::Uploaded {
+          distributor: REGISTERED.name.to_string(),
+          release: path.clone(),
+          name,
+          id: file,
+        })?;
+      }
+
+      // the metadata is updated last, so that the version is only listed once its files are there
+      let (mut versions, latest) = self.versions(&client, &directory).await?;
+
+      // alphas and betas are listed, but don't replace the latest release
+      let latest = match release.version_type {
+        VersionType::Release => Some(version.clone()),
+        VersionType::Beta | VersionType::Alpha => latest,
+      };
+
+      if !versions.contains(version) {
+        versions.push(version.clone());
+        self
+          .put_metadata(
+            &client,
+            &directory,
+            &artifact_id,
+            &versions,
+            latest.as_deref(),
+          )
+          .await?;
+
+        journal.lock().created(Created::Distributed {
+          distributor: &REGISTERED,
+          kind: "version",
+          id: path.clone(),
+          url: location.clone(),
+          tag: None,
+        });
+      } else if force {
+        self
+          .put_metadata(
+            &client,
+            &directory,
+            &artifact_id,
+            &versions,
+            latest.as_deref(),
+          )
+          .await?;
+      }
+
+      info!(
+        "Deployed {}:{}:{} to {}",
+        group_id, artifact_id, version, location
+      );
+
+      journal.lock().record(Step::Published {
+        distributor: REGISTERED.name.to_string(),
+        name: artifact_id,
+        id: path,
+        url: location.clone(),
+      })?;
+
+      urls.push(location);
+    }
+
+    Ok(urls)
+  }
+
+  async fn rollback(
+    &self,
+    kind: &str,
+    id: &str,
+    _tag: Option<&str>,
+  ) -> Result<(), error::AxolotlError> {
+    let client = network::client()?;
+
+    if kind == "file" {
+      for file in std::iter::once(id.to_string()).chain(
+        CHECKSUMS
+          .iter()
+          .map(|checksum| format!("{}.{}", id, checksum)),
+      ) {
+        network::retry("Deleting the file", || self.delete(&client, &file)).await?;
+      }
+
+      return Ok(());
+    }
+
+    let (directory, version) = id
+      .rsplit_once('/')
+      .ok_or_else(|| error::AxolotlError::Error(format!("Invalid Maven version {}", id)))?;
+    let artifact_id = directory.rsplit('/').next().unwrap_or(directory);
+
+    let (mut versions, latest) = self.versions(&client, directory).await?;
+    versions.retain(|existing| existing != version);
+
+    // the version types of the others aren't known, so the release before it can't be told apart
+    // from alphas and betas, and is left for Maven to work out from the versions
+    let latest = latest.filter(|latest| latest != version);
+
+    if versions.is_empty() {
+      let metadata = format!("{}/maven-metadata.xml", directory);
+
+      for file in std::iter::once(metadata.clone()).chain(
+        CHECKSUMS
+          .iter()
+          .map(|checksum| format!("{}.{}", metadata, checksum)),
+      ) {
+        network::retry("Deleting the metadata", || self.delete(&client, &file)).await?;
+      }
+
+      return Ok(());
+    }
+
+    self
+      .put_metadata(
+        &client,
+        directory,
+        artifact_id,
+        &versions,
+        latest.as_deref(),
+      )
+      .await
+  }
+}
+
+impl MavenDistributor<'_> {
+  /// The artifact id of the target, suffixed with its name if it has one
+  fn artifact_id(&self, target: &Target) -> String {
+    match &target.name {
+      Some(name) => format!("{}-{}", self.configuration.artifact_id, name),
+      None => self.configuration.artifact_id.clone(),
+    }
+  }
+
+  /// Where a path in the repository can be found
+  fn location(&self, path: &str) -> String {
+    format!("{}/{}", self.configuration.url.trim_end_matches('/'), path)
+  }
+
+  /// A request for a path in the repository, authenticated with the key if there is one
+  fn request(&self, client: &reqwest::Client, method: Method, path: &str) -> RequestBuilder {
+    let request = client.request(method, self.location(path));
+
+    match (&self.configuration.username, &self.token) {
+      (Some(username), token) => request.basic_auth(username, token.as_ref()),
+      (None, Some(token)) => request.bearer_auth(token),
+      (None, None) => request,
+    }
+  }
+
+  async fn exists(
+    &self,
+    client: &reqwest::Client,
+    path: &str,
+  ) -> Result<bool, error::AxolotlError> {
+    if let Some(folder) = &self.folder {
+      return Ok(folder.join(path).is_file());
+    }
+
+    match send(self.request(client, Method::HEAD, path)).await {
+      Ok(_) => Ok(true),
+      Err(error::AxolotlError::MavenError { status, .. }) if status == StatusCode::NOT_FOUND => {
+        Ok(false)
+      }
+      Err(error) => Err(error),
+    }
+  }
+
+  /// The content of a file in the repository, if it exists
+  async fn get(
+    &self,
+    client: &reqwest::Client,
+    path: &str,
+  ) -> Result<Option<String>, error::AxolotlError> {
+    if let Some(folder) = &self.folder {
+      return match fs::read_to_string(folder.join(path)) {
+        Ok(content) => Ok(Some(content)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error.into()),
+      };
+    }
+
+    match send(self.request(client, Method::GET, path)).await {
+      Ok(content) => Ok(Some(content)),
+      Err(error::AxolotlError::MavenError { status, .. }) if status == StatusCode::NOT_FOUND => {
+        Ok(None)
+      }
+      Err(error) => Err(error),
+    }
+  }
+
+  async fn put(
+    &self,
+    client: &reqwest::Client,
+    path: &str,
+    content: Vec<u8>,
+  ) -> Result<(), error::AxolotlError> {
+    if let Some(folder) = &self.folder {
+      return write(&folder.join(path), |file| fs::write(file, &content));
+    }
+
+    send(self.request(client, Method::PUT, path).body(content))
+      .await
+      .map(|_| ())
+  }
+
+  /// Stream an asset to the repository
+  async fn upload(
+    &self,
+    client: &reqwest::Client,
+    path: &str,
+    asset: &Asset,
+    progress: &ProgressBar,
+  ) -> Result<(), error::AxolotlError> {
+    if let Some(folder) = &self.folder {
+      write(&folder.join(path), |file| {
+        fs::copy(asset.file.path(), file).map(|_| ())
+      })?;
+      progress.set_position(asset.length()?);
+
+      return Ok(());
+    }
+
+    send(
+      self
+        .request(client, Method::PUT, path)
+        .header(CONTENT_LENGTH, asset.length()?)
+        .body(asset.stream(progress).await?),
+    )
+    .await
+    .map(|_| ())
+  }
+
+  async fn delete(&self, client: &reqwest::Client, path: &str) -> Result<(), error::AxolotlError> {
+    if let Some(folder) = &self.folder {
+      let file = folder.join(path);
+
+      match fs::remove_file(&file) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error.into()),
+        _ => {}
+      }
+
+      // the folder of the version goes with its last file, and fails to be removed before that
+      if let Some(parent) = file.parent() {
+        let _ = fs::remove_dir(parent);
+      }
+
+      return Ok(());
+    }
+
+    send(self.request(client, Method::DELETE, path))
+      .await
+      .map(|_| ())
+  }
+
+  async fn put_checksums(
+    &self,
+    client: &reqwest::Client,
+    path: &str,
+    checksums: [String; 4],
+  ) -> Result<(), error::AxolotlError> {
+    for (extension, checksum) in CHECKSUMS.iter().zip(checksums) {
+      let file = format!("{}.{}", path, extension);
+
+      network::retry(&format!("Uploading the {} checksum", extension), || {
+        self.put(client, &file, checksum.clone().into_bytes())
+      })
+      .await?;
+    }
+
+    Ok(())
+  }
+
+  /// The versions listed in the metadata of the artifact, and its latest release
+  async fn versions(
+    &self,
+    client: &reqwest::Client,
+    directory: &str,
+  ) -> Result<(Vec<String>, Option<String>), error::AxolotlError> {
+    let path = format!("{}/maven-metadata.xml", directory);
+    let metadata = network::retry("Getting the metadata", || self.get(client, &path)).await?;
+
+    Ok(match metadata.as_deref() {
+      Some(metadata) => (versions(metadata), release(metadata)),
+      None => (vec![], None),
+    })
+  }
+
+  async fn put_metadata(
+    &self,
+    client: &reqwest::Client,
+    directory: &str,
+    artifact_id: &str,
+    versions: &[String],
+    release: Option<&str>,
+  ) -> Result<(), error::AxolotlError> {
+    let path = format!("{}/maven-metadata.xml", directory);
+    let content =
+      metadata(&self.configuration.group_id, artifact_id, versions, release).into_bytes();
+
+    network::retry("Updating the metadata", || {
+      self.put(client, &path, content.clone())
+    })
+    .await?;
+
+    self
+      .put_checksums(client, &path, checksums(content.as_slice())?)
+      .await
+  }
+}
+
+/// The files deployed for a target, named the way Maven expects
+fn files<'a>(
+  target: &'a Target,
+  group_id: &str,
+  artifact_id: &str,
+  version: &str,
+) -> Vec<(String, Content<'a>)> {
+  let mut files = vec![(
+    format!(
+      "{}-{}.{}",
+      artifact_id,
+      version,
+      extension(&target.artifact.name)
+    ),
+    Content::Asset(&target.artifact),
+  )];
+
+  if let Some(sources) = &target.sources {
+    files.push((
+      format!("{}-{}-sources.jar", artifact_id, version),
+      Content::Asset(sources),
+    ));
+  }
+
+  files.push((
+    format!("{}-{}.pom", artifact_id, version),
+    Content::Generated(pom(group_id, artifact_id, version, &target.artifact.name)),
+  ));
+
+  files
+}
+
+fn extension(name: &str) -> &str {
+  Path::new(name)
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .unwrap_or("jar")
+}
+
+/// A minimal POM, without dependencies
+fn pom(group_id: &str, artifact_id: &str, version: &str, artifact: &str) -> String {
+  let packaging = match extension(artifact) {
+    "jar" => String::new(),
+    extension => format!("  <packaging>{}</packaging>\n", escape(extension)),
+  };
+
+  format!(
+    r#"<?xml version="1.0" encoding="UTF-8"?>
+<project xmlns="http://maven.apache.org/POM/4.0.0" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://maven.apache.org/POM/4.0.0 https://maven.apache.org/xsd/maven-4.0.0.xsd">
+  <modelVersion>4.0.0</modelVersion>
+  <groupId>{}</groupId>
+  <artifactId>{}</artifactId>
+  <version>{}</version>
+{}</project>
+"#,
+    escape(group_id),
+    escape(artifact_id),
+    escape(version),
+    packaging
+  )
+}
+
+/// The metadata of an artifact, with the last version as the latest one, and the release only if
+/// there is one
+fn metadata(
+  group_id: &str,
+  artifact_id: &str,
+  versions: &[String],
+  release: Option<&str>,
+) -> String {
+  let latest = versions
+    .last()
+    .map(|version| escape(version))
+    .unwrap_or_default();
+  let release = release
+    .map(|release| format!("    <release>{}</release>\n", escape(release)))
+    .unwrap_or_default();
+
+  format!(
+    r#"<?xml version="1.0" encoding="UTF-8"?>
+<metadata>
+  <groupId>{}</groupId>
+  <artifactId>{}</artifactId>
+  <versioning>
+    <latest>{}</latest>
+{}    <versions>
+{}    </versions>
+    <lastUpdated>{}</lastUpdated>
+  </versioning>
+</metadata>
+"#,
+    escape(group_id),
+    escape(artifact_id),
+    latest,
+    release,
+    versions
+      .iter()
+      .map(|version| format!("      <version>{}</version>\n", escape(version)))
+      .collect::<String>(),
+    Utc::now().format("%Y%m%d%H%M%S")
+  )
+}
+
+/// The versions listed in existing metadata, in order
+fn versions(metadata: &str) -> Vec<String> {
+  let Some(start) = metadata.find("<versions>") else {
+    return vec![];
+  };
+  let end = metadata[start..]
+    .find("</versions>")
+    .map_or(metadata.len(), |end| start + end);
+
+  metadata[start..end]
+    .split("<version>")
+    .skip(1)
+    .filter_map(|version| version.split_once("</version>"))
+    .map(|(version, _)| unescape(version.trim()))
+    .collect()
+}
+
+/// The latest release in existing metadata, if there is one
+fn release(metadata: &str) -> Option<String> {
+  let (_, rest) = metadata.split_once("<release>")?;
+  let (release, _) = rest.split_once("</release>")?;
+
+  Some(unescape(release.trim())).filter(|release| !release.is_empty())
+}
+
+/// The checksums of the content, read in chunks so that artifacts aren't loaded whole
+fn checksums(mut content: impl Read) -> io::Result<[String; 4]> {
+  let mut md5 = md5::Md5::new();
+  let mut sha1 = sha1::Sha1::new();
+  let mut sha256 = sha2::Sha256::new();
+  let mut sha512 = sha2::Sha512::new();
+  let mut buffer = vec![0; 64 * 1024];
+
+  loop {
+    let read = content.read(&mut buffer)?;
+
+    if read == 0 {
+      break;
+    }
+
+    md5.update(&buffer[..read]);
+    sha1.update(&buffer[..read]);
+    sha256.update(&buffer[..read]);
+    sha512.update(&buffer[..read]);
+  }
+
+  Ok([
+    hex::encode(md5.finalize()),
+    hex::encode(sha1.finalize()),
+    hex::encode(sha256.finalize()),
+    hex::encode(sha512.finalize()),
+  ])
+}
+
+fn escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+    .replace('\'', "&apos;")
+}
+
+fn unescape(text: &str) -> String {
+  text
+    .replace("&lt;", "<")
+    .replace("&gt;", ">")
+    .replace("&quot;", "\"")
+    .replace("&apos;", "'")
+    .replace("&amp;", "&")
+}
+
+/// Write a file in a `file://` repository, creating its folders
+fn write(
+  file: &Path,
+  write: impl FnOnce(&Path) -> io::Result<()>,
+) -> Result<(), error::AxolotlError> {
+  if let Some(parent) = file.parent() {
+    fs::create_dir_all(parent)?;
+  }
+
+  Ok(write(file)?)
+}
+
+async fn send(request: RequestBuilder) -> Result<String, error::AxolotlError> {
+  network::send(request, |status, content| error::AxolotlError::MavenError {
+    status,
+    content,
+  })
+  .await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const METADATA: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<metadata>
+  <groupId>com.example</groupId>
+  <artifactId>mod</artifactId>
+  <versioning>
+    <latest>1.1.0-beta</latest>
+    <release>1.0.0</release>
+    <versions>
+      <version>0.9.0</version>
+      <version> 1.0.0 </version>
+      <version>1.1.0-beta</version>
+    </versions>
+    <lastUpdated>20240101000000</lastUpdated>
+  </versioning>
+</metadata>
+"#;
+
+  fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+  }
+
+  #[test]
+  fn versions_are_listed_in_order() {
+    assert_eq!(
+      versions(METADATA),
+      strings(&["0.9.0", "1.0.0", "1.1.0-beta"])
+    );
+  }
+
+  #[test]
+  fn versions_are_unescaped() {
+    assert_eq!(
+      versions("<versions><version>1.0&amp;2&lt;3&gt;</version></versions>"),
+      strings(&["1.0&2<3>"])
+    );
+  }
+
+  #[test]
+  fn versions_without_a_list_are_empty() {
+    assert!(versions("<metadata><versioning></versioning></metadata>").is_empty());
+    assert!(versions("<versions></versions>").is_empty());
+    assert!(versions("").is_empty());
+  }
+
+  #[test]
+  fn versions_ignore_versions_outside_the_list() {
+    assert_eq!(
+      versions("<version>2.0.0</version><versions><version>1.0.0</version></versions>"),
+      strings(&["1.0.0"])
+    );
+  }
+
+  #[test]
+  fn release_is_read() {
+    assert_eq!(release(METADATA), Some("1.0.0".to_string()));
+    assert_eq!(release("<release> </release>"), None);
+    assert_eq!(release("<versions></versions>"), None);
+  }
+
+  #[test]
+  fn metadata_round_trips() {
+    let listed = strings(&["1.0.0", "1.1.0 <beta>"]);
+    let written = metadata("com.example", "mod", &listed, Some("1.0.0"));
+
+    assert_eq!(versions(&written), listed);
+    assert_eq!(release(&written), Some("1.0.0".to_string()));
+    assert!(written.contains("<latest>1.1.0 &lt;beta&gt;</latest>"));
+    assert!(written.contains("<groupId>com.example</groupId>"));
+  }
+
+  #[test]
+  fn metadata_without_a_release() {
+    let written = metadata("com.example", "mod", &strings(&["1.0.0-beta"]), None);
+
+    assert!(!written.contains("<release>"));
+    assert_eq!(release(&written), None);
+  }
+
+  #[test]
+  fn escape_round_trips() {
+    let text = r#"a & b < c > "d" 'e' &amp;"#;
+
+    assert_eq!(unescape(&escape(text)), text);
+  }
+
+  #[test]
+  fn checksums_are_hex_digests() {
+    assert_eq!(
+      checksums("abc".as_bytes()).unwrap(),
+      [
+        "900150983cd24fb0d6963f7d28e17f72".to_string(),
+        "a9993e364706816aba3e25717850c26c9cd0d89d".to_string(),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".to_string(),
+        "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f".to_string(),
+      ]
+    );
+  }
+}
@@ -0,0 +1,618 @@
+use crate::{error, network};
+use modrinth_api::{apis, models::game_version_tag};
+use serde_json::Value as Json;
+use std::{cmp::Ordering, fs::File, io::Read, path::Path};
+use toml::Value as Toml;
+use zip::ZipArchive;
+
+/// What a built mod jar declares about itself
+#[derive(Debug, Default)]
+pub struct Metadata {
+  pub version: Option<String>,
+  pub loaders: Vec<String>,
+  pub minecraft: Option<Requirement>,
+}
+
+impl Metadata {
+  pub fn read(path: &Path) -> Result<Self, error::AxolotlError> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut metadata = Metadata::default();
+
+    if let Some(json) = entry(&mut archive, "fabric.mod.json")? {
+      let json: Json = serde_json::from_str(&json)?;
+
+      metadata.merge(
+        "fabric",
+        json["version"].as_str().map(str::to_string),
+        json["depends"].get("minecraft").and_then(Requirement::json),
+      );
+    }
+
+    if let Some(json) = entry(&mut archive, "quilt.mod.json")? {
+      let json: Json = serde_json::from_str(&json)?;
+      let loader = &json["quilt_loader"];
+
+      let minecraft = loader["depends"].as_array().and_then(|depends| {
+        depends
+          .iter()
+          .find(|dependency| dependency["id"] == "minecraft")
+          .and_then(|dependency| Requirement::json(&dependency["versions"]))
+      });
+
+      metadata.merge(
+        "quilt",
+        loader["version"].as_str().map(str::to_string),
+        minecraft,
+      );
+    }
+
+    for (file, default_loader) in [
+      ("META-INF/mods.toml", "forge"),
+      ("META-INF/neoforge.mods.toml", "neoforge"),
+    ] {
+      if let Some(toml) = entry(&mut archive, file)? {
+        let toml: Toml = toml::from_str(&toml)?;
+
+        let dependencies = toml
+          .get("dependencies")
+          .and_then(Toml::as_table)
+          .map(|table| {
+            table
+              .values()
+              .filter_map(Toml::as_array)
+              .flatten()
+              .collect::<Vec<_>>()
+          })
+          .unwrap_or_default();
+        let dependency = |id: &str| {
+          dependencies
+            .iter()
+            .find(|dependency| dependency.get("modId").and_then(Toml::as_str) == Some(id))
+        };
+
+        // older neoforge versions still use mods.toml, but depend on neoforge rather than forge
+        let loader = if dependency("neoforge").is_some() {
+          "neoforge"
+        } else {
+          default_loader
+        };
+
+        let version = match toml
+          .get("mods")
+          .and_then(Toml::as_array)
+          .and_then(|mods| mods.first())
+          .and_then(|first| first.get("version"))
+          .and_then(Toml::as_str)
+        {
+          Some("${file.jarVersion}") => {
+            entry(&mut archive, "META-INF/MANIFEST.MF")?.and_then(|manifest| {
+              manifest.lines().find_map(|line| {
+                line
+                  .strip_prefix("Implementation-Version:")
+                  .map(|version| version.trim().to_string())
+              })
+            })
+          }
+          version => version.map(str::to_string),
+        };
+
+        let minecraft = dependency("minecraft")
+          .and_then(|dependency| dependency.get("versionRange"))
+          .and_then(Toml::as_str)
+          .and_then(Requirement::maven);
+
+        metadata.merge(loader, version, minecraft);
+      }
+    }
+
+    Ok(metadata)
+  }
+
+  fn merge(&mut self, loader: &str, version: Option<String>, minecraft: Option<Requirement>) {
+    if !self.loaders.iter().any(|l| l == loader) {
+      self.loaders.push(loader.to_string());
+    }
+
+    if self.version.is_none() {
+      self.version = version;
+    }
+
+    if self.minecraft.is_none() {
+      self.minecraft = minecraft;
+    }
+  }
+}
+
+fn entry(
+  archive: &mut ZipArchive<File>,
+  name: &str,
+) -> Result<Option<String>, error::AxolotlError> {
+  match archive.by_name(name) {
+    Ok(mut file) => {
+      let mut contents = String::new();
+      file.read_to_string(&mut contents)?;
+      Ok(Some(contents))
+    }
+    Err(zip::result::ZipError::FileNotFound) => Ok(None),
+    Err(error) => Err(error.into()),
+  }
+}
+
+/// One end of a range of Minecraft versions
+#[derive(Debug, Clone, PartialEq)]
+struct Bound {
+  version: Vec<u32>,
+  inclusive: bool,
+}
+
+/// A contiguous range of Minecraft versions; missing bounds are unbounded
+#[derive(Debug, Clone, PartialEq, Default)]
+struct Range {
+  min: Option<Bound>,
+  max: Option<Bound>,
+}
+
+/// A set of versions, matched if any of the ranges match
+#[derive(Debug, Clone, PartialEq)]
+pub struct Requirement(Vec<Range>);
+
+fn parse_version(version: &str) -> Option<Vec<u32>> {
+  // strip prerelease markers such as the trailing `-` in `>=1.20-`
+  let version = version.split(['-', '+']).next()?;
+  version.split('.').map(|part| part.parse().ok()).collect()
+}
+
+fn compare(a: &[u32], b: &[u32]) -> Ordering {
+  (0..a.len().max(b.len()))
+    .map(|i| a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0)))
+    .find(|ordering| ordering.is_ne())
+    .unwrap_or(Ordering::Equal)
+}
+
+fn next(version: &[u32], index: usize) -> Vec<u32> {
+  let mut next = version[..=index].to_vec();
+  next[index] += 1;
+  next
+}
+
+impl Range {
+  fn point(version: Vec<u32>) -> Self {
+    Range {
+      min: Some(Bound {
+        version: version.clone(),
+        inclusive: true,
+      }),
+      max: Some(Bound {
+        version,
+        inclusive: true,
+      }),
+    }
+  }
+
+  fn between(min: Vec<u32>, max: Vec<u32>) -> Self {
+    Range {
+      min: Some(Bound {
+        version: min,
+        inclusive: true,
+      }),
+      max: Some(Bound {
+        version: max,
+        inclusive: false,
+      }),
+    }
+  }
+
+  fn contains(&self, version: &[u32]) -> bool {
+    let above = self
+      .min
+      .as_ref()
+      .is_none_or(|min| match compare(version, &min.version) {
+        Ordering::Greater => true,
+        Ordering::Equal => min.inclusive,
+        Ordering::Less => false,
+      });
+    let below = self
+      .max
+      .as_ref()
+      .is_none_or(|max| match compare(version, &max.version) {
+        Ordering::Less => true,
+        Ordering::Equal => max.inclusive,
+        Ordering::Greater => false,
+      });
+
+    above && below
+  }
+
+  fn intersect(self, other: Range) -> Range {
+    let min = match (self.min, other.min) {
+      (Some(a), Some(b)) => Some(match compare(&a.version, &b.version) {
+        Ordering::Greater => a,
+        Ordering::Less => b,
+        Ordering::Equal => Bound {
+          inclusive: a.inclusive && b.inclusive,
+          ..a
+        },
+      }),
+      (a, b) => a.or(b),
+    };
+    let max = match (self.max, other.max) {
+      (Some(a), Some(b)) => Some(match compare(&a.version, &b.version) {
+        Ordering::Less => a,
+        Ordering::Greater => b,
+        Ordering::Equal => Bound {
+          inclusive: a.inclusive && b.inclusive,
+          ..a
+        },
+      }),
+      (a, b) => a.or(b),
+    };
+
+    Range { min, max }
+  }
+
+  /// Parse a single Fabric/Quilt predicate such as `>=1.20`, `~1.20.1` or `1.20.x`
+  fn predicate(predicate: &str) -> Option<Range> {
+    let predicate = predicate.trim();
+
+    if predicate.is_empty() || predicate == "*" {
+      return Some(Range::default());
+    }
+
+    for (operator, inclusive, lower) in [
+      (">=", true, true),
+      ("<=", true, false),
+      (">", false, true),
+      ("<", false, false),
+    ] {
+      if let Some(version) = predicate.strip_prefix(operator) {
+        let bound = Some(Bound {
+          version: parse_version(version)?,
+          inclusive,
+        });
+
+        return Some(if lower {
+          Range {
+            min: bound,
+            max: None,
+          }
+        } else {
+          Range {
+            min: None,
+            max: bound,
+          }
+        });
+      }
+    }
+
+    if let Some(version) = predicate.strip_prefix('~') {
+      let version = parse_version(version)?;
+      let index = if version.len() > 1 { 1 } else { 0 };
+      return Some(Range::between(version.clone(), next(&version, index)));
+    }
+
+    if let Some(version) = predicate.strip_prefix('^') {
+      let version = parse_version(version)?;
+      return Some(Range::between(version.clone(), next(&version, 0)));
+    }
+
+    let predicate = predicate.strip_prefix('=').unwrap_or(predicate);
+
+    if let Some(prefix) = predicate
+      .strip_suffix(".x")
+      .or(predicate.strip_suffix(".X"))
+      .or(predicate.strip_suffix(".*"))
+    {
+      let version = parse_version(prefix)?;
+      let last = version.len() - 1;
+      return Some(Range::between(version.clone(), next(&version, last)));
+    }
+
+    parse_version(predicate).map(Range::point)
+  }
+}
+
+/// Parse space-separated Fabric/Quilt predicates, all of which must match
+fn fabric(requirement: &str) -> Option<Range> {
+  requirement
+    .split_whitespace()
+    .map(Range::predicate)
+    .try_fold(Range::default(), |a, b| Some(a.intersect(b?)))
+}
+
+impl Requirement {
+  /// Parse a requirement made of space-separated predicates such as `>=0.100 <1`
+  pub fn parse(requirement: &str) -> Option<Requirement> {
+    fabric(requirement).map(|range| Requirement(vec![range]))
+  }
+
+  /// Parse a Fabric/Quilt version requirement: a string of space-separated predicates, an array
+  /// (any of), or a Quilt `{ "any": [...] }`/`{ "all": [...] }` object
+  fn json(json: &Json) -> Option<Requirement> {
+    match json {
+      Json::String(requirement) => Requirement::parse(requirement),
+      Json::Array(requirements) => requirements
+        .iter()
+        .map(|requirement| requirement.as_str().and_then(fabric))
+        .collect::<Option<_>>()
+        .map(Requirement),
+      Json::Object(object) => match (object.get("any"), object.get("all")) {
+        (Some(any), _) => Requirement::json(any),
+        (_, Some(Json::Array(all))) => all
+          .iter()
+          .map(|requirement| requirement.as_str().and_then(fabric))
+          .try_fold(Range::default(), |a, b| Some(a.intersect(b?)))
+          .map(|range| Requirement(vec![range])),
+        _ => None,
+      },
+      _ => None,
+    }
+  }
+
+  /// Parse a Maven version range as used by (Neo)Forge, such as `[1.20.1,1.21)` or `[1.21]`
+  fn maven(requirement: &str) -> Option<Requirement> {
+    let mut ranges = vec![];
+    let mut rest = requirement.trim();
+
+    while !rest.is_empty() {
+      rest = rest.trim_start_matches([',', ' ']);
+
+      let Some(open) = rest.chars().next() else {
+        break;
+      };
+
+      if open != '[' && open != '(' {
+        // a bare version is a soft requirement, meaning at least that version
+        let end = rest.find(',').unwrap_or(rest.len());
+        ranges.push(Range {
+          min: Some(Bound {
+            version: parse_version(&rest[..end])?,
+            inclusive: true,
+          }),
+          max: None,
+        });
+        rest = &rest[end..];
+        continue;
+      }
+
+      let end = rest.find([']', ')'])?;
+      let close = rest[end..].chars().next()?;
+      let inner = &rest[1..end];
+      rest = &rest[end + 1..];
+
+      let bound = |version: &str, inclusive: bool| -> Option<Option<Bound>> {
+        let version = version.trim();
+
+        if version.is_empty() {
+          Some(None)
+        } else {
+          Some(Some(Bound {
+            version: parse_version(version)?,
+            inclusive,
+          }))
+        }
+      };
+
+      ranges.push(match inner.split_once(',') {
+        Some((min, max)) => Range {
+          min: bound(min, open == '[')?,
+          max: bound(max, close == ']')?,
+        },
+        None => Range::point(parse_version(inner)?),
+      });
+    }
+
+    Some(Requirement(ranges))
+  }
+
+  pub fn matches(&self, version: &str) -> bool {
+    parse_version(version)
+      .is_some_and(|version| self.0.iter().any(|range| range.contains(&version)))
+  }
+
+  /// The versions this requirement names, if it only consists of exact versions
+  fn exact(&self) -> Option<Vec<String>> {
+    self
+      .0
+      .iter()
+      .map(|range| match (&range.min, &range.max) {
+        (Some(min), Some(max)) if min == max && min.inclusive => Some(
+          min
+            .version
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join("."),
+        ),
+        _ => None,
+      })
+      .collect()
+  }
+
+  /// Resolve the requirement to a list of Minecraft release versions known to Modrinth
+  pub async fn game_versions(&self) -> Result<Vec<String>, error::AxolotlError> {
+    if let Some(versions) = self.exact() {
+      return Ok(versions);
+    }
+
+    let configuration = network::modrinth(None)?;
+
+    Ok(
+      network::retry("Listing the Minecraft versions", || async {
+        Ok(apis::tags_api::version_list(&configuration).await?)
+      })
+      .await?
+      .into_iter()
+      .filter(|tag| tag.version_type == game_version_tag::VersionType::Release)
+      .map(|tag| tag.version)
+      .filter(|version| self.matches(version))
+      .collect(),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  /// Which of the versions the requirement matches
+  fn matching<'a>(requirement: &Requirement, versions: &[&'a str]) -> Vec<&'a str> {
+    versions
+      .iter()
+      .copied()
+      .filter(|version| requirement.matches(version))
+      .collect()
+  }
+
+  /// A Fabric requirement made of space-separated predicates
+  fn predicates(requirement: &str) -> Option<Requirement> {
+    Requirement::json(&json!(requirement))
+  }
+
+  const VERSIONS: &[&str] = &["1.19.4", "1.20", "1.20.1", "1.20.4", "1.21", "1.21.1"];
+
+  #[test]
+  fn parse_version_strips_prerelease_markers() {
+    assert_eq!(parse_version("1.20.1"), Some(vec![1, 20, 1]));
+    assert_eq!(parse_version("1.20-"), Some(vec![1, 20]));
+    assert_eq!(parse_version("1.21+build.1"), Some(vec![1, 21]));
+    assert_eq!(parse_version("23w13a"), None);
+  }
+
+  #[test]
+  fn fabric_comparisons() {
+    let requirement = predicates(">=1.20 <1.21").unwrap();
+    assert_eq!(
+      matching(&requirement, VERSIONS),
+      ["1.20", "1.20.1", "1.20.4"]
+    );
+
+    let requirement = predicates(">1.20.1 <=1.21").unwrap();
+    assert_eq!(matching(&requirement, VERSIONS), ["1.20.4", "1.21"]);
+
+    let requirement = predicates(">=1.20-").unwrap();
+    assert_eq!(
+      matching(&requirement, VERSIONS),
+      ["1.20", "1.20.1", "1.20.4", "1.21", "1.21.1"]
+    );
+  }
+
+  #[test]
+  fn fabric_tilde_and_caret() {
+    let requirement = predicates("~1.20.1").unwrap();
+    assert_eq!(matching(&requirement, VERSIONS), ["1.20.1", "1.20.4"]);
+
+    let requirement = predicates("^1.20").unwrap();
+    assert_eq!(
+      matching(&requirement, VERSIONS),
+      ["1.20", "1.20.1", "1.20.4", "1.21", "1.21.1"]
+    );
+  }
+
+  #[test]
+  fn fabric_wildcards() {
+    let requirement = predicates("1.20.x").unwrap();
+    assert_eq!(
+      matching(&requirement, VERSIONS),
+      ["1.20", "1.20.1", "1.20.4"]
+    );
+    assert_eq!(predicates("1.20.X"), predicates("1.20.*"));
+
+    for any in ["*", ""] {
+      let requirement = predicates(any).unwrap();
+      assert_eq!(matching(&requirement, VERSIONS), VERSIONS);
+    }
+  }
+
+  #[test]
+  fn fabric_exact_versions() {
+    let requirement = predicates("1.20.1").unwrap();
+    assert_eq!(matching(&requirement, VERSIONS), ["1.20.1"]);
+    assert_eq!(predicates("=1.20.1"), Some(requirement));
+  }
+
+  #[test]
+  fn fabric_rejects_invalid_versions() {
+    assert_eq!(predicates(">=1.20 <abc"), None);
+  }
+
+  #[test]
+  fn json_requirements() {
+    let requirement = Requirement::json(&json!(["1.20.1", ">=1.21"])).unwrap();
+    assert_eq!(
+      matching(&requirement, VERSIONS),
+      ["1.20.1", "1.21", "1.21.1"]
+    );
+
+    let requirement = Requirement::json(&json!({ "any": ["1.20", "1.21"] })).unwrap();
+    assert_eq!(matching(&requirement, VERSIONS), ["1.20", "1.21"]);
+
+    let requirement = Requirement::json(&json!({ "all": [">=1.20.1", "<1.21"] })).unwrap();
+    assert_eq!(matching(&requirement, VERSIONS), ["1.20.1", "1.20.4"]);
+
+    assert_eq!(Requirement::json(&json!(1)), None);
+    assert_eq!(Requirement::json(&json!({ "none": [] })), None);
+  }
+
+  #[test]
+  fn maven_ranges() {
+    let requirement = Requirement::maven("[1.20.1,1.21)").unwrap();
+    assert_eq!(matching(&requirement, VERSIONS), ["1.20.1", "1.20.4"]);
+
+    let requirement = Requirement::maven("(1.20.1,1.21]").unwrap();
+    assert_eq!(matching(&requirement, VERSIONS), ["1.20.4", "1.21"]);
+
+    let requirement = Requirement::maven("(,1.20]").unwrap();
+    assert_eq!(matching(&requirement, VERSIONS), ["1.19.4", "1.20"]);
+
+    let requirement = Requirement::maven("[1.21,)").unwrap();
+    assert_eq!(matching(&requirement, VERSIONS), ["1.21", "1.21.1"]);
+  }
+
+  #[test]
+  fn maven_exact_and_soft_versions() {
+    let requirement = Requirement::maven("[1.21]").unwrap();
+    assert_eq!(matching(&requirement, VERSIONS), ["1.21"]);
+
+    let requirement = Requirement::maven("1.20.4").unwrap();
+    assert_eq!(
+      matching(&requirement, VERSIONS),
+      ["1.20.4", "1.21", "1.21.1"]
+    );
+  }
+
+  #[test]
+  fn maven_unions() {
+    let requirement = Requirement::maven("[1.19.4],[1.20.1,1.20.4]").unwrap();
+    assert_eq!(
+      matching(&requirement, VERSIONS),
+      ["1.19.4", "1.20.1", "1.20.4"]
+    );
+  }
+
+  #[test]
+  fn maven_rejects_invalid_ranges() {
+    assert_eq!(Requirement::maven("[1.20.1,1.21"), None);
+    assert_eq!(Requirement::maven("[abc]"), None);
+  }
+
+  #[test]
+  fn exact_lists_only_exact_versions() {
+    assert_eq!(
+      Requirement::maven("[1.20.1],[1.21]").unwrap().exact(),
+      Some(vec!["1.20.1".to_string(), "1.21".to_string()])
+    );
+    assert_eq!(
+      Requirement::json(&json!(["1.20", "1.20.1"]))
+        .unwrap()
+        .exact(),
+      Some(vec!["1.20".to_string(), "1.20.1".to_string()])
+    );
+    assert_eq!(predicates(">=1.20").unwrap().exact(), None);
+    assert_eq!(
+      Requirement::maven("[1.20,1.20]").unwrap().exact(),
+      Some(vec!["1.20".to_string()])
+    );
+    assert_eq!(Requirement::maven("(1.20,1.20]").unwrap().exact(), None);
+  }
+}
@@ -1,61 +1,778 @@
-use crate::error::{self, AxolotlError};
+use crate::{
+  changelog,
+  configuration::{
+    ChangelogTransformations, Configuration, Modrinth, ModrinthDependency, ModrinthStatus,
+  },
+  distributor::{self, Context, Distributor, Registered},
+  error::{self, AxolotlError},
+  journal::Step,
+  keys::Keys,
+  metadata::Requirement,
+  network, progress,
+  publish::{Asset, Target},
+  rollback::Created,
+  util::VersionType,
+  PublishArguments,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use indicatif::ProgressBar;
 use modrinth_api::{
-  apis::{self, configuration, versions_api::CreateVersionError, ResponseContent},
-  models,
+  apis::{self, configuration, projects_api, version_files_api, versions_api, ResponseContent},
+  models::{
+    creatable_version::{RequestedStatus, Status},
+    CreatableVersion, EditableVersion, Version, VersionDependency,
+  },
+};
+use reqwest::{
+  header::{AUTHORIZATION, USER_AGENT},
+  multipart::Form,
+  Method, RequestBuilder,
 };
-use reqwest::multipart::Part;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::future::Future;
+
+pub static REGISTERED: Registered = Registered {
+  name: "modrinth",
+  alias: "m",
+  title: "Modrinth",
+  create,
+};
+
+fn create<'a>(
+  configuration: &'a Configuration,
+  keys: &Keys,
+  dry_run: bool,
+) -> Option<Box<dyn Distributor + 'a>> {
+  let modrinth = configuration.modrinth.as_ref()?;
+
+  Some(Box::new(ModrinthDistributor {
+    configuration: modrinth,
+    transformations: configuration
+      .changelog
+      .as_ref()
+      .and_then(|changelog| changelog.modrinth.as_ref()),
+    links: distributor::links(configuration),
+    token: distributor::token(keys, &REGISTERED, dry_run)?,
+    status: (modrinth.status, None),
+    project: String::new(),
+    existing: vec![],
+    dependencies: vec![],
+    changelog: None,
+  }))
+}
+
+/// Uploads a version of the `[modrinth]` project for every target
+pub struct ModrinthDistributor<'a> {
+  configuration: &'a Modrinth,
+  transformations: Option<&'a ChangelogTransformations>,
+  /// The repository the changelog links to, as Modrinth projects have none of their own
+  links: Option<changelog::Links>,
+  /// Only missing for a dry run
+  token: Option<String>,
+  /// The status versions are created with, along with when they are released if scheduled
+  status: (ModrinthStatus, Option<DateTime<Utc>>),
+  /// The canonical id of the project, looked up by `prepare` along with the rest
+  project: String,
+  existing: Vec<Version>,
+  /// The dependencies of each target
+  dependencies: Vec<Vec<VersionDependency>>,
+  changelog: Option<String>,
+}
+
+#[async_trait]
+impl Distributor for ModrinthDistributor<'_> {
+  fn registered(&self) -> &'static Registered {
+    &REGISTERED
+  }
+
+  fn validate(&mut self, arguments: &PublishArguments) -> Result<(), AxolotlError> {
+    self.status = status(
+      self.configuration,
+      arguments.modrinth_status,
+      arguments.modrinth_release_at.clone(),
+    )?;
+
+    Ok(())
+  }
+
+  async fn prepare(&mut self, context: &Context<'_, '_>) -> Result<(), AxolotlError> {
+    let config = network::modrinth(self.token.clone())?;
+    let release = context.release;
+
+    self.changelog = context.changelog(self.transformations, self.links.as_ref())?;
+    self.project = project_id(&config, &self.configuration.id).await?;
+    self.existing = network::retry("Listing the versions", || async {
+      Ok(versions_api::get_project_versions(&config, &self.project, None, None, None).await?)
+    })
+    .await?;
+
+    // resolve the dependencies of every target before uploading anything
+    self.dependencies = vec![];
+
+    for target in &release.targets {
+      let mut target_dependencies = vec![];
+
+      for dependency in self
+        .configuration
+        .dependencies
+        .iter()
+        .chain(&target.dependencies)
+      {
+        target_dependencies.push(
+          self::dependency(&config, dependency, &target.game_versions, &target.loaders).await?,
+        );
+      }
+
+      self.dependencies.push(target_dependencies);
+    }
+
+    Ok(())
+  }
+
+  async fn publish(&self, context: &Context<'_, '_>) -> Result<Vec<String>, AxolotlError> {
+    let Context {
+      release,
+      journal,
+      force,
+      dry_run,
+      ..
+    } = *context;
+    let Modrinth {
+      featured,
+      requested_status,
+      ..
+    } = self.configuration;
+
+    let config = network::modrinth(self.token.clone())?;
+    let (status, release_at) = self.status;
+    let release_at = release_at.map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true));
+
+    // drafts and scheduled versions are created as drafts that remember the status to release to
+    let (status, requested) = match status {
+      ModrinthStatus::Listed => (Status::Listed, None),
+      ModrinthStatus::Unlisted => (Status::Unlisted, None),
+      ModrinthStatus::Archived => (Status::Archived, None),
+      ModrinthStatus::Draft | ModrinthStatus::Scheduled => (Status::Draft, Some(*requested_status)),
+    };
+
+    let mut urls = vec![];
+
+    for (target, dependencies) in release.targets.iter().zip(&self.dependencies) {
+      let data = CreatableVersion {
+        name: match &target.name {
+          Some(name) => format!("{} ({})", release.tag, name),
+          None => release.tag.clone(),
+        },
+        version_number: release.version.clone(),
+        changelog: Some(self.changelog.clone()),
+        dependencies: dependencies.clone(),
+        game_versions: target.game_versions.clone(),
+        version_type: release.version_type.into(),
+        loaders: target.loaders.clone(),
+        featured: *featured,
+        status: Some(status),
+        requested_status: requested.map(Some),
+        project_id: self.project.clone(),
+        file_parts: target.assets().map(|asset| asset.name.clone()).collect(),
+        primary_file: Some(target.artifact.name.clone()),
+      };
+
+      let published = journal
+        .lock()
+        .published(REGISTERED.name, &data.name)
+        .map(|(_, url)| url.to_string());
+
+      if let Some(url) = published {
+        info!(
+          "Version {} has already been published, skipping it",
+          data.name
+        );
+        urls.push(url);
+        continue;
+      }
+
+      // targets share the version number, so versions are told apart by their name
+      if let Some(version) = self
+        .existing
+        .iter()
+        .find(|version| version.version_number == data.version_number && version.name == data.name)
+      {
+        let url = format!(
+          "https://modrinth.com/project/{}/version/{}",
+          version.project_id, version.id
+        );
+
+        let name = data.name.clone();
+
+        reuse_version(
+          &config,
+          version,
+          data,
+          release.version_type,
+          target,
+          force,
+          dry_run,
+        )
+        .await?;
+
+        // a version whose scheduling failed is still a draft
+        if journal.lock().unscheduled(REGISTERED.name, &name) {
+          match &release_at {
+            Some(release_at) if dry_run => {
+              info!("Would schedule version {} for {}", name, release_at)
+            }
+            Some(release_at) => {
+              schedule_version(&config, version, release_at, *requested_status).await?;
+
+              info!("Scheduled version {} for {}", name, release_at);
+            }
+            None => warn!(
+              "Version {} was created to be scheduled, but no release time was given - it is \
+               left as a draft",
+              name
+            ),
+          }
+        }
 
+        journal.lock().record(Step::Published {
+          distributor: REGISTERED.name.to_string(),
+          name,
+          id: version.id.clone(),
+          url: url.clone(),
+        })?;
+
+        urls.push(url);
+        continue;
+      }
+
+      if dry_run {
+        info!(
+          "Would create Modrinth version\n{}",
+          serde_json::to_string_pretty(&data)?
+        );
+
+        if let Some(release_at) = &release_at {
+          info!("Would schedule version {} for {}", data.name, release_at);
+        }
+
+        continue;
+      }
+
+      info!("Uploading version {}", data.name);
+
+      let bars = target
+        .assets()
+        .map(|asset| Ok((asset, asset.progress(REGISTERED.title)?)))
+        .collect::<Result<Vec<_>, AxolotlError>>()?;
+
+      let version = create_version(&config, &data, &bars).await;
+
+      for (_, bar) in &bars {
+        progress::finish(bar, &version);
+      }
+
+      let version = version?;
+
+      let url = format!(
+        "https://modrinth.com/project/{}/version/{}",
+        version.project_id, version.id
+      );
+
+      info!("Uploaded version to {}", url);
+
+      journal.lock().created(Created::Distributed {
+        distributor: &REGISTERED,
+        kind: "version",
+        id: version.id.clone(),
+        url: url.clone(),
+        tag: None,
+      });
+
+      if let Some(release_at) = &release_at {
+        journal.lock().record(Step::Unscheduled {
+          distributor: REGISTERED.name.to_string(),
+          name: version.name.clone(),
+          id: version.id.clone(),
+        })?;
+
+        schedule_version(&config, &version, release_at, *requested_status).await?;
+
+        info!("Scheduled version {} for {}", version.name, release_at);
+      }
+
+      journal.lock().record(Step::Published {
+        distributor: REGISTERED.name.to_string(),
+        name: version.name,
+        id: version.id,
+        url: url.clone(),
+      })?;
+
+      urls.push(url);
+    }
+
+    Ok(urls)
+  }
+
+  async fn rollback(&self, _kind: &str, id: &str, _tag: Option<&str>) -> Result<(), AxolotlError> {
+    let config = network::modrinth(self.token.clone())?;
+
+    network::retry("Deleting the version", || async {
+      Ok(versions_api::delete_version(&config, id).await?)
+    })
+    .await
+  }
+}
+
+/// Create a version with the assets as its files
 pub async fn create_version(
   configuration: &configuration::Configuration,
-  data: models::CreatableVersion,
-  file: Part,
-) -> Result<models::Version, error::AxolotlError> {
-  let local_var_configuration = configuration;
-
-  let local_var_client = &local_var_configuration.client;
-
-  let local_var_uri_str = format!("{}/version", local_var_configuration.base_path);
-  let mut local_var_req_builder =
-    local_var_client.request(reqwest::Method::POST, local_var_uri_str.as_str());
-
-  if let Some(ref local_var_user_agent) = local_var_configuration.user_agent {
-    local_var_req_builder =
-      local_var_req_builder.header(reqwest::header::USER_AGENT, local_var_user_agent.clone());
-  }
-  if let Some(ref local_var_apikey) = local_var_configuration.api_key {
-    let local_var_key = local_var_apikey.key.clone();
-    let local_var_value = match local_var_apikey.prefix {
-      Some(ref local_var_prefix) => format!("{} {}", local_var_prefix, local_var_key),
-      None => local_var_key,
-    };
-    local_var_req_builder = local_var_req_builder.header("Authorization", local_var_value);
-  };
-  let mut local_var_form = reqwest::multipart::Form::new();
-  local_var_form = local_var_form
-    .text("data", serde_json::to_string(&data)?)
-    .part("file", file);
-  local_var_req_builder = local_var_req_builder.multipart(local_var_form);
+  data: &CreatableVersion,
+  assets: &[(&Asset, ProgressBar)],
+) -> Result<Version, AxolotlError> {
+  let content = send(
+    configuration,
+    &format!("Uploading version {}", data.name),
+    (Method::POST, "/version".to_string()),
+    true,
+    AxolotlError::ModrinthCreateVersionError,
+    |request| async move {
+      let mut data = serde_json::to_value(data)?;
+      data["file_types"] = file_types(assets);
+      let form = files(assets).await?.text("data", data.to_string());
+
+      Ok(request.multipart(form))
+    },
+  )
+  .await?;
+
+  serde_json::from_str(&content)
+    .map_err(|error| AxolotlError::ModrinthCreateVersionError(apis::Error::Serde(error)))
+}
+
+/// Upload additional files to an existing version
+pub async fn add_files(
+  configuration: &configuration::Configuration,
+  version: &Version,
+  assets: &[(&Asset, ProgressBar)],
+) -> Result<(), AxolotlError> {
+  let names = assets
+    .iter()
+    .map(|(asset, _)| asset.name.as_str())
+    .collect::<Vec<_>>()
+    .join(", ");
+
+  send(
+    configuration,
+    &format!("Uploading {}", names),
+    (
+      Method::POST,
+      format!("/version/{}/file", apis::urlencode(&version.id)),
+    ),
+    true,
+    AxolotlError::ModrinthAddFilesError,
+    |request| async move {
+      let data = json!({ "file_types": file_types(assets) });
+
+      Ok(request.multipart(files(assets).await?.text("data", data.to_string())))
+    },
+  )
+  .await
+  .map(|_| ())
+}
 
-  let local_var_req = local_var_req_builder.build()?;
-  let local_var_resp = local_var_client.execute(local_var_req).await?;
+/// Schedule a version to move to the requested status at the given time; the generated client's
+/// schedule only accepts project statuses
+pub async fn schedule_version(
+  configuration: &configuration::Configuration,
+  version: &Version,
+  time: &str,
+  requested_status: RequestedStatus,
+) -> Result<(), AxolotlError> {
+  let body = json!({
+    "time": time,
+    "requested_status": requested_status,
+  });
+
+  send(
+    configuration,
+    &format!("Scheduling version {}", version.name),
+    (
+      Method::POST,
+      format!("/version/{}/schedule", apis::urlencode(&version.id)),
+    ),
+    false,
+    AxolotlError::ModrinthScheduleVersionError,
+    |request| async { Ok(request.json(&body)) },
+  )
+  .await
+  .map(|_| ())
+}
+
+/// Send a request the generated client can't make the way it makes its own, retrying it (only if it
+/// can't have arrived when it `creates` something), and return the body of the response or the
+/// error made from a failed one
+///
+/// The body is added for every attempt, so that files are read from the start again
+async fn send<E, F, R>(
+  configuration: &configuration::Configuration,
+  action: &str,
+  (method, path): (Method, String),
+  creates: bool,
+  error: fn(apis::Error<E>) -> AxolotlError,
+  body: F,
+) -> Result<String, AxolotlError>
+where
+  E: DeserializeOwned,
+  F: Fn(RequestBuilder) -> R,
+  R: Future<Output = Result<RequestBuilder, AxolotlError>>,
+{
+  let attempt = || async {
+    let mut request = configuration.client.request(
+      method.clone(),
+      format!("{}{}", configuration.base_path, path),
+    );
+
+    if let Some(user_agent) = &configuration.user_agent {
+      request = request.header(USER_AGENT, user_agent);
+    }
+
+    if let Some(api_key) = &configuration.api_key {
+      request = request.header(
+        AUTHORIZATION,
+        match &api_key.prefix {
+          Some(prefix) => format!("{} {}", prefix, api_key.key),
+          None => api_key.key.clone(),
+        },
+      );
+    }
+
+    let response = body(request).await?.send().await?;
+    let status = response.status();
+    let wait = network::rate_limit(response.headers());
+    let content = response.text().await?;
+
+    if status.is_client_error() || status.is_server_error() {
+      let entity = serde_json::from_str(&content).ok();
 
-  let local_var_status = local_var_resp.status();
-  let local_var_content = local_var_resp.text().await?;
+      return Err(network::rate_limited(
+        error(apis::Error::ResponseError(ResponseContent {
+          status,
+          content,
+          entity,
+        })),
+        wait,
+      ));
+    }
 
-  if !local_var_status.is_client_error() && !local_var_status.is_server_error() {
-    serde_json::from_str(&local_var_content)
-      .map_err(|error| AxolotlError::ModrinthCreateVersionError(apis::Error::Serde(error)))
+    network::pace(wait).await;
+
+    Ok(content)
+  };
+
+  if creates {
+    network::retry_create(action, attempt).await
   } else {
-    let local_var_entity: Option<CreateVersionError> =
-      serde_json::from_str(&local_var_content).ok();
-    let local_var_error = ResponseContent {
-      status: local_var_status,
-      content: local_var_content,
-      entity: local_var_entity,
+    network::retry(action, attempt).await
+  }
+}
+
+/// A form with the assets as its files, read from the start
+async fn files(assets: &[(&Asset, ProgressBar)]) -> Result<Form, AxolotlError> {
+  let mut form = Form::new();
+
+  for (asset, bar) in assets {
+    form = form.part(asset.name.clone(), asset.part(bar).await?);
+  }
+
+  Ok(form)
+}
+
+/// The types of the assets that aren't the mod itself, such as sources jars, by their part name;
+/// the generated client's versions lack them
+fn file_types(assets: &[(&Asset, ProgressBar)]) -> serde_json::Value {
+  assets
+    .iter()
+    .filter_map(|(asset, _)| Some((asset.name.clone(), json!(file_type(&asset.name)?))))
+    .collect::<serde_json::Map<_, _>>()
+    .into()
+}
+
+fn file_type(name: &str) -> Option<&'static str> {
+  [
+    ("-sources.jar", "sources-jar"),
+    ("-dev.jar", "dev-jar"),
+    ("-javadoc.jar", "javadoc-jar"),
+  ]
+  .into_iter()
+  .find(|(suffix, _)| name.ends_with(suffix))
+  .map(|(_, file_type)| file_type)
+}
+
+/// The canonical id of a project given its id or slug
+pub async fn project_id(
+  configuration: &configuration::Configuration,
+  project: &str,
+) -> Result<String, error::AxolotlError> {
+  network::retry(&format!("Looking up {}", project), || async {
+    Ok(projects_api::check_project_validity(configuration, project).await?)
+  })
+  .await?
+  .id
+  .ok_or_else(|| AxolotlError::Error(format!("Could not find the Modrinth project {}", project)))
+}
+
+/// Resolve a configured dependency for a version supporting the game versions and loaders,
+/// looking up slugs and picking the newest version matching a version requirement
+pub async fn dependency(
+  configuration: &configuration::Configuration,
+  dependency: &ModrinthDependency,
+  game_versions: &[String],
+  loaders: &[String],
+) -> Result<VersionDependency, error::AxolotlError> {
+  let project_id = match (&dependency.id, &dependency.slug) {
+    (Some(_), Some(_)) => {
+      return Err(AxolotlError::Error(
+        "A Modrinth dependency may have an id or a slug, but not both".to_string(),
+      ))
+    }
+    (Some(project), None) | (None, Some(project)) => {
+      Some(project_id(configuration, project).await?)
+    }
+    (None, None) => None,
+  };
+
+  let version_id = match (&project_id, &dependency.version_id, &dependency.version) {
+    (_, Some(_), Some(_)) => {
+      return Err(AxolotlError::Error(
+        "A Modrinth dependency may have a version_id or a version, but not both".to_string(),
+      ))
+    }
+    (_, Some(version_id), None) => Some(version_id.clone()),
+    (Some(project_id), None, Some(version)) => {
+      let requirement = Requirement::parse(version)
+        .ok_or_else(|| AxolotlError::Error(format!("Invalid version requirement {}", version)))?;
+
+      // versions are listed newest first
+      let loaders_filter = serde_json::to_string(loaders)?;
+      let game_versions_filter = serde_json::to_string(game_versions)?;
+
+      let resolved = network::retry(
+        &format!("Listing the versions of {}", project_id),
+        || async {
+          Ok(
+            versions_api::get_project_versions(
+              configuration,
+              project_id,
+              Some(&loaders_filter),
+              Some(&game_versions_filter),
+              None,
+            )
+            .await?,
+          )
+        },
+      )
+      .await?
+      .into_iter()
+      .find(|candidate| requirement.matches(&candidate.version_number))
+      .ok_or_else(|| {
+        AxolotlError::Error(format!(
+          "No version of {} matches {} for {} on {}",
+          dependency.slug.as_ref().unwrap_or(project_id),
+          version,
+          game_versions.join(", "),
+          loaders.join(", ")
+        ))
+      })?;
+
+      info!(
+        "Resolved the dependency {} {} to {} ({})",
+        dependency.slug.as_ref().unwrap_or(project_id),
+        version,
+        resolved.version_number,
+        resolved.id
+      );
+
+      Some(resolved.id)
+    }
+    (None, None, Some(_)) => {
+      return Err(AxolotlError::Error(
+        "A Modrinth dependency with a version requirement needs an id or a slug".to_string(),
+      ))
+    }
+    (_, None, None) => None,
+  };
+
+  if project_id.is_none() && version_id.is_none() && dependency.file_name.is_none() {
+    return Err(AxolotlError::Error(
+      "A Modrinth dependency needs an id, a slug, a version_id or a file_name".to_string(),
+    ));
+  }
+
+  Ok(VersionDependency {
+    version_id: version_id.map(Some),
+    project_id: project_id.map(Some),
+    file_name: dependency.file_name.clone().map(Some),
+    dependency_type: dependency.dependency_type,
+  })
+}
+
+/// Upload the files missing from an existing Modrinth version, replacing the details and the files
+/// that are already there if forced
+async fn reuse_version(
+  config: &apis::configuration::Configuration,
+  version: &Version,
+  data: CreatableVersion,
+  version_type: VersionType,
+  target: &Target,
+  force: bool,
+  dry_run: bool,
+) -> Result<(), AxolotlError> {
+  if dry_run {
+    info!(
+      "Would {} the existing Modrinth version {}",
+      if force { "update" } else { "reuse" },
+      version.name
+    );
+  } else if force {
+    info!("Updating the existing Modrinth version {}", version.name);
+
+    let details = EditableVersion {
+      name: Some(data.name),
+      changelog: Some(data.changelog.flatten()),
+      dependencies: Some(data.dependencies),
+      game_versions: Some(data.game_versions),
+      version_type: Some(version_type.into()),
+      loaders: Some(data.loaders),
+      featured: Some(data.featured),
+      ..EditableVersion::new()
     };
 
-    Err(AxolotlError::ModrinthCreateVersionError(
-      apis::Error::ResponseError(local_var_error),
-    ))
+    network::retry(&format!("Updating version {}", version.name), || async {
+      Ok(versions_api::modify_version(config, &version.id, Some(details.clone())).await?)
+    })
+    .await?;
+  } else {
+    info!("Reusing the existing Modrinth version {}", version.name);
+  }
+
+  let mut missing = vec![];
+
+  for asset in target.assets() {
+    if let Some(file) = version
+      .files
+      .iter()
+      .find(|file| file.filename == asset.name)
+    {
+      if !force {
+        info!("{} has already been uploaded, skipping it", asset.name);
+        continue;
+      }
+
+      if dry_run {
+        info!("Would replace {}", asset.name);
+        continue;
+      }
+
+      info!("Replacing {}", asset.name);
+
+      let hash = file.hashes.sha1.as_deref().ok_or_else(|| {
+        AxolotlError::Error(format!(
+          "Modrinth did not report the hash of {}",
+          asset.name
+        ))
+      })?;
+
+      network::retry(&format!("Deleting {}", asset.name), || async {
+        Ok(version_files_api::delete_file_from_hash(config, hash, "sha1", Some(&version.id)).await?)
+      })
+      .await?;
+    } else if dry_run {
+      info!("Would upload {}", asset.name);
+      continue;
+    }
+
+    missing.push(asset);
+  }
+
+  if !missing.is_empty() {
+    let names = missing
+      .iter()
+      .map(|asset| asset.name.as_str())
+      .collect::<Vec<_>>()
+      .join(", ");
+
+    info!("Uploading {}", names);
+
+    let bars = missing
+      .iter()
+      .map(|asset| Ok((*asset, asset.progress(REGISTERED.title)?)))
+      .collect::<Result<Vec<_>, AxolotlError>>()?;
+
+    let uploaded = add_files(config, version, &bars).await;
+
+    for (_, bar) in &bars {
+      progress::finish(bar, &uploaded);
+    }
+
+    uploaded?;
+  }
+
+  Ok(())
+}
+
+/// The status Modrinth versions are created with, along with when they are released if scheduled
+fn status(
+  configuration: &Modrinth,
+  status: Option<ModrinthStatus>,
+  release_at: Option<String>,
+) -> Result<(ModrinthStatus, Option<DateTime<Utc>>), AxolotlError> {
+  let status = status.unwrap_or(configuration.status);
+  let release_at = release_at.or_else(|| configuration.release_at.clone());
+
+  match (status, release_at) {
+    (ModrinthStatus::Scheduled, Some(release_at)) => {
+      let release_at = DateTime::parse_from_rfc3339(&release_at)
+        .map_err(|error| {
+          AxolotlError::Error(format!(
+            "Invalid Modrinth release time {} ({}) - expected an RFC 3339 timestamp such as 2024-01-01T12:00:00Z",
+            release_at, error
+          ))
+        })?
+        .with_timezone(&Utc);
+
+      if release_at <= Utc::now() {
+        return Err(AxolotlError::Error(format!(
+          "The Modrinth release time {} is in the past",
+          release_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+        )));
+      }
+
+      Ok((status, Some(release_at)))
+    }
+    (ModrinthStatus::Scheduled, None) => Err(AxolotlError::Error(
+      "Scheduled Modrinth versions need a release time - set release_at in [modrinth] or pass --modrinth-release-at".to_string(),
+    )),
+    (status, Some(_)) => {
+      warn!("Ignoring the Modrinth release time since the versions are not scheduled");
+
+      Ok((status, None))
+    }
+    (status, None) => Ok((status, None)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn file_type_marks_sources_and_development_jars() {
+    assert_eq!(file_type("mod-1.0.0-sources.jar"), Some("sources-jar"));
+    assert_eq!(file_type("mod-1.0.0-dev.jar"), Some("dev-jar"));
+    assert_eq!(file_type("mod-1.0.0-javadoc.jar"), Some("javadoc-jar"));
+    assert_eq!(file_type("mod-1.0.0.jar"), None);
+    assert_eq!(file_type("mod-sources-1.0.0.jar"), None);
   }
 }
@@ -0,0 +1,478 @@
+use crate::{configuration::Network, error::AxolotlError};
+use chrono::Utc;
+use modrinth_api::apis;
+use octocrab::{service::middleware::retry::RetryConfig, Octocrab};
+use reqwest::{
+  header::{HeaderMap, HeaderValue, AUTHORIZATION},
+  RequestBuilder, StatusCode,
+};
+use std::{future::Future, sync::OnceLock, time::Duration};
+
+static NETWORK: OnceLock<Network> = OnceLock::new();
+
+/// The longest wait between attempts when no rate limit says otherwise
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// GitHub asks to wait at least a minute after hitting a secondary rate limit without saying how
+/// long
+const SECONDARY_RATE_LIMIT: Duration = Duration::from_secs(60);
+
+/// Use the configured retries and timeouts for every request that follows
+pub fn configure(network: Option<&Network>) {
+  let _ = NETWORK.set(network.cloned().unwrap_or_default());
+}
+
+fn network() -> &'static Network {
+  NETWORK.get_or_init(Network::default)
+}
+
+/// A Modrinth client with the configured timeouts, authenticated if a token is given
+pub fn modrinth(token: Option<String>) -> Result<apis::configuration::Configuration, AxolotlError> {
+  let mut headers = HeaderMap::new();
+
+  if let Some(token) = token {
+    headers.insert(AUTHORIZATION, HeaderValue::from_str(&token)?);
+  }
+
+  let mut configuration = apis::configuration::Configuration::new();
+  configuration.client = builder().default_headers(headers).build()?;
+
+  Ok(configuration)
+}
+
+/// A plain client with the configured timeouts, for requests the API clients can't make
+pub fn client() -> Result<reqwest::Client, AxolotlError> {
+  Ok(builder().build()?)
+}
+
+fn builder() -> reqwest::ClientBuilder {
+  let network = network();
+
+  reqwest::Client::builder()
+    .user_agent(concat!("axolotl/", env!("CARGO_PKG_VERSION")))
+    .connect_timeout(Duration::from_secs(network.connect_timeout))
+    .read_timeout(Duration::from_secs(network.timeout))
+}
+
+/// A GitHub client with the configured timeouts
+pub fn github(token: String) -> Result<Octocrab, AxolotlError> {
+  let network = network();
+
+  Ok(
+    Octocrab::builder()
+      .personal_token(token)
+      // octocrab retries immediately, so requests are retried by `retry` instead
+      .add_retry_config(RetryConfig::None)
+      .set_connect_timeout(Some(Duration::from_secs(network.connect_timeout)))
+      .set_read_timeout(Some(Duration::from_secs(network.timeout)))
+      .set_write_timeout(Some(Duration::from_secs(network.timeout)))
+      .build()?,
+  )
+}
+
+/// Send a request, retrying it with exponential backoff and jitter if it fails because of the
+/// connection, the server or a rate limit
+///
+/// The request is rebuilt for every attempt, so uploads are streamed from their files again rather
+/// than reusing a consumed body
+pub async fn retry<T, F, R>(action: &str, request: F) -> Result<T, AxolotlError>
+where
+  F: FnMut() -> R,
+  R: Future<Output = Result<T, AxolotlError>>,
+{
+  attempt(action, true, request).await
+}
+
+/// Send a request that creates something, such as a release or an upload, retrying it like
+/// `retry` only if it failed before the server could act on it: when connecting failed, or with a
+/// 429 or 503 response (or the 403 of GitHub's rate limit), even if the response asked to wait
+///
+/// Retrying one that timed out or failed with another error could create it twice
+pub async fn retry_create<T, F, R>(action: &str, request: F) -> Result<T, AxolotlError>
+where
+  F: FnMut() -> R,
+  R: Future<Output = Result<T, AxolotlError>>,
+{
+  attempt(action, false, request).await
+}
+
+async fn attempt<T, F, R>(action: &str, idempotent: bool, mut request: F) -> Result<T, AxolotlError>
+where
+  F: FnMut() -> R,
+  R: Future<Output = Result<T, AxolotlError>>,
+{
+  let retries = network().retries;
+  let mut attempt = 0;
+
+  loop {
+    let error = match request().await {
+      Ok(value) => return Ok(value),
+      Err(error) => error,
+    };
+
+    let Some((reason, wait)) = transient(&error, idempotent) else {
+      return Err(error);
+    };
+
+    if attempt >= retries {
+      return Err(error);
+    }
+
+    let wait = wait.unwrap_or_else(|| backoff(attempt));
+    attempt += 1;
+
+    warn!(
+      "{} failed ({}), retrying in {:.1}s ({}/{})",
+      action,
+      reason,
+      wait.as_secs_f64(),
+      attempt,
+      retries
+    );
+
+    tokio::time::sleep(wait).await;
+  }
+}
+
+/// Send a request and return the body of a successful response, or the error made from the status
+/// and body of a failed one, keeping the wait asked for by a rate limited response so that the
+/// retry honors it
+pub async fn send(
+  request: RequestBuilder,
+  error: fn(StatusCode, String) -> AxolotlError,
+) -> Result<String, AxolotlError> {
+  let response = request.send().await?;
+
+  let status = response.status();
+  let wait = rate_limit(response.headers());
+  let content = response.text().await?;
+
+  if status.is_success() {
+    Ok(content)
+  } else {
+    Err(rate_limited(error(status, content), wait))
+  }
+}
+
+/// How long the response asks to wait before the next request, from `Retry-After` or the rate
+/// limit headers once none are remaining
+pub fn rate_limit(headers: &HeaderMap) -> Option<Duration> {
+  let header = |name: &str| {
+    headers
+      .get(name)
+      .and_then(|value| value.to_str().ok())
+      .and_then(|value| value.trim().parse::<u64>().ok())
+  };
+
+  if let Some(seconds) = header("retry-after") {
+    return Some(Duration::from_secs(seconds));
+  }
+
+  if header("x-ratelimit-remaining") != Some(0) {
+    return None;
+  }
+
+  // Modrinth counts the seconds until the reset, GitHub gives the time of the reset
+  let reset = header("x-ratelimit-reset")?;
+  let seconds = if reset > 1_000_000_000 {
+    reset.saturating_sub(Utc::now().timestamp() as u64)
+  } else {
+    reset
+  };
+
+  Some(Duration::from_secs(seconds + 1))
+}
+
+/// Keep the wait asked for by a failed response so that the retry honors it
+pub fn rate_limited(error: AxolotlError, wait: Option<Duration>) -> AxolotlError {
+  match wait {
+    Some(wait) => AxolotlError::RateLimited {
+      source: Box::new(error),
+      wait,
+    },
+    None => error,
+  }
+}
+
+/// Wait for the rate limit to reset if a successful response used up the last request
+pub async fn pace(wait: Option<Duration>) {
+  if let Some(wait) = wait {
+    info!(
+      "The rate limit has been reached, waiting {}s for it to reset",
+      wait.as_secs()
+    );
+
+    tokio::time::sleep(wait).await;
+  }
+}
+
+/// Half of the exponential delay plus up to as much again at random, so that retries don't line up
+fn backoff(attempt: u32) -> Duration {
+  let delay = Duration::from_secs(1)
+    .saturating_mul(2u32.saturating_pow(attempt))
+    .min(MAX_BACKOFF);
+  let half = delay.as_millis() as u64 / 2;
+
+  Duration::from_millis(half + fastrand::u64(0..=half))
+}
+
+/// Why the request may succeed if it's sent again, along with how long to wait first if known
+fn transient(error: &AxolotlError, idempotent: bool) -> Option<(String, Option<Duration>)> {
+  match error {
+    // any failed response can ask to wait, but only some can be sent again without acting twice
+    AxolotlError::RateLimited { source, wait } if idempotent || github_rate_limit(source) => {
+      Some(("rate limited".to_string(), Some(*wait)))
+    }
+    AxolotlError::RateLimited { source, wait } => {
+      transient(source, idempotent).map(|(reason, _)| (reason, Some(*wait)))
+    }
+    AxolotlError::ReqwestError(error) => reqwest_error(error, idempotent),
+    AxolotlError::GithubError(error) => github_error(error, idempotent),
+    AxolotlError::GitHubUploadError { status, .. } => status_code(status.as_u16(), idempotent),
+    AxolotlError::ForgejoError { status, .. } => status_code(status.as_u16(), idempotent),
+    AxolotlError::GitLabError { status, .. } => status_code(status.as_u16(), idempotent),
+    AxolotlError::MavenError { status, .. } => status_code(status.as_u16(), idempotent),
+    AxolotlError::ModrinthProjectValidityError(error) => modrinth_error(error, idempotent),
+    AxolotlError::ModrinthProjectError(error) => modrinth_error(error, idempotent),
+    AxolotlError::ModrinthCreateVersionError(error) => modrinth_error(error, idempotent),
+    AxolotlError::ModrinthAddFilesError(error) => modrinth_error(error, idempotent),
+    AxolotlError::ModrinthDeleteFileError(error) => modrinth_error(error, idempotent),
+    AxolotlError::ModrinthDeleteVersionError(error) => modrinth_error(error, idempotent),
+    AxolotlError::ModrinthScheduleVersionError(error) => modrinth_error(error, idempotent),
+    AxolotlError::ModrinthProjectVersionsError(error) => modrinth_error(error, idempotent),
+    AxolotlError::ModrinthModifyVersionError(error) => modrinth_error(error, idempotent),
+    AxolotlError::ModrinthVersionListError(error) => modrinth_error(error, idempotent),
+    AxolotlError::ModrinthError(error) => modrinth_error(error, idempotent),
+    _ => None,
+  }
+}
+
+/// Only a failed connection is certain not to have reached the server, a timeout or a failure
+/// while sending may have been after it received the request
+fn reqwest_error(error: &reqwest::Error, idempotent: bool) -> Option<(String, Option<Duration>)> {
+  if error.is_connect() {
+    Some(("connection error".to_string(), None))
+  } else if idempotent && error.is_timeout() {
+    Some(("timed out".to_string(), None))
+  } else if idempotent && (error.is_request() || error.is_body()) {
+    Some(("connection error".to_string(), None))
+  } else {
+    error
+      .status()
+      .and_then(|status| status_code(status.as_u16(), idempotent))
+  }
+}
+
+fn modrinth_error<T>(
+  error: &apis::Error<T>,
+  idempotent: bool,
+) -> Option<(String, Option<Duration>)> {
+  match error {
+    apis::Error::Reqwest(error) => reqwest_error(error, idempotent),
+    apis::Error::ResponseError(response) => status_code(response.status.as_u16(), idempotent),
+    _ => None,
+  }
+}
+
+fn github_error(error: &octocrab::Error, idempotent: bool) -> Option<(String, Option<Duration>)> {
+  match error {
+    octocrab::Error::GitHub { source, .. }
+      if source
+        .message
+        .to_lowercase()
+        .contains("secondary rate limit") =>
+    {
+      Some((
+        "secondary rate limit".to_string(),
+        Some(SECONDARY_RATE_LIMIT),
+      ))
+    }
+    octocrab::Error::GitHub { source, .. } => status_code(source.status_code.as_u16(), idempotent),
+    // these don't tell whether the request was sent before the connection failed
+    octocrab::Error::Hyper { .. } | octocrab::Error::Service { .. } if idempotent => {
+      Some(("connection error".to_string(), None))
+    }
+    _ => None,
+  }
+}
+
+/// GitHub refuses requests over its primary rate limit with a 403 response rather than a 429 one
+fn github_rate_limit(error: &AxolotlError) -> bool {
+  match error {
+    AxolotlError::GithubError(error) => matches!(
+      &**error,
+      octocrab::Error::GitHub { source, .. } if source.status_code == StatusCode::FORBIDDEN
+    ),
+    AxolotlError::GitHubUploadError { status, .. } => *status == StatusCode::FORBIDDEN,
+    _ => false,
+  }
+}
+
+/// A 503 response is sent by servers that are down or overloaded without handling the request,
+/// unlike other server errors
+fn status_code(status: u16, idempotent: bool) -> Option<(String, Option<Duration>)> {
+  match status {
+    429 => Some(("rate limited".to_string(), None)),
+    503 => Some((format!("status {}", status), None)),
+    500..=599 if idempotent => Some((format!("status {}", status), None)),
+    _ => None,
+  }
+}
+
+/// Percent-encode a path segment, such as a project path or a tag, which may contain slashes
+pub fn encode(segment: &str) -> String {
+  segment
+    .bytes()
+    .map(|byte| match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+        (byte as char).to_string()
+      }
+      _ => format!("%{:02X}", byte),
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::cell::Cell;
+
+  fn forgejo(status: u16) -> AxolotlError {
+    AxolotlError::ForgejoError {
+      status: StatusCode::from_u16(status).unwrap(),
+      content: String::new(),
+    }
+  }
+
+  /// A failed response asking to be retried right away
+  fn limited(error: AxolotlError) -> AxolotlError {
+    rate_limited(error, Some(Duration::ZERO))
+  }
+
+  /// Send a request failing with the errors in turn before it succeeds, returning how many times
+  /// it was sent
+  async fn send_failing(idempotent: bool, errors: Vec<AxolotlError>) -> (bool, usize) {
+    let errors = Cell::new(errors);
+    let sent = Cell::new(0);
+    let request = || {
+      sent.set(sent.get() + 1);
+      let mut remaining = errors.take();
+      let result = if remaining.is_empty() {
+        Ok(())
+      } else {
+        Err(remaining.remove(0))
+      };
+      errors.set(remaining);
+      async { result }
+    };
+
+    let result = if idempotent {
+      retry("Sending", request).await
+    } else {
+      retry_create("Sending", request).await
+    };
+
+    (result.is_ok(), sent.get())
+  }
+
+  #[test]
+  fn rate_limit_reads_retry_after() {
+    let mut headers = HeaderMap::new();
+    headers.insert("retry-after", HeaderValue::from_static("7"));
+
+    assert_eq!(rate_limit(&headers), Some(Duration::from_secs(7)));
+  }
+
+  #[test]
+  fn rate_limit_waits_for_the_reset_once_none_are_remaining() {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-ratelimit-remaining", HeaderValue::from_static("1"));
+    headers.insert("x-ratelimit-reset", HeaderValue::from_static("30"));
+    assert_eq!(rate_limit(&headers), None);
+
+    headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+    assert_eq!(rate_limit(&headers), Some(Duration::from_secs(31)));
+
+    let reset = Utc::now().timestamp() + 100;
+    headers.insert("x-ratelimit-reset", HeaderValue::from(reset));
+    let wait = rate_limit(&headers).unwrap();
+    assert!(wait > Duration::from_secs(95) && wait <= Duration::from_secs(101));
+  }
+
+  #[test]
+  fn server_errors_are_only_retried_when_idempotent() {
+    assert!(transient(&forgejo(500), true).is_some());
+    assert!(transient(&forgejo(500), false).is_none());
+    assert!(transient(&forgejo(503), false).is_some());
+    assert!(transient(&forgejo(429), false).is_some());
+    assert!(transient(&forgejo(404), true).is_none());
+  }
+
+  #[test]
+  fn rate_limited_failures_are_only_retried_if_not_acted_on() {
+    let wait = Some(Duration::from_secs(5));
+
+    let failed = rate_limited(forgejo(429), wait);
+    assert_eq!(transient(&failed, false).unwrap().1, wait);
+
+    let failed = rate_limited(forgejo(503), wait);
+    assert_eq!(transient(&failed, false).unwrap().1, wait);
+
+    let failed = rate_limited(forgejo(500), wait);
+    assert!(transient(&failed, false).is_none());
+    assert_eq!(transient(&failed, true).unwrap().1, wait);
+
+    let failed = rate_limited(forgejo(422), wait);
+    assert!(transient(&failed, false).is_none());
+  }
+
+  #[test]
+  fn github_rate_limits_are_retried_when_creating() {
+    let forbidden = || AxolotlError::GitHubUploadError {
+      status: StatusCode::FORBIDDEN,
+      content: String::new(),
+    };
+
+    assert!(transient(&forbidden(), false).is_none());
+    assert!(transient(&limited(forbidden()), false).is_some());
+  }
+
+  #[tokio::test]
+  async fn retry_sends_again_until_it_succeeds() {
+    assert_eq!(
+      send_failing(true, vec![limited(forgejo(500)), limited(forgejo(429))]).await,
+      (true, 3)
+    );
+  }
+
+  #[tokio::test]
+  async fn retry_gives_up_after_the_configured_retries() {
+    let retries = network().retries as usize;
+    let errors = (0..=retries).map(|_| limited(forgejo(429))).collect();
+
+    assert_eq!(send_failing(true, errors).await, (false, retries + 1));
+  }
+
+  #[tokio::test]
+  async fn retry_create_does_not_send_again_after_the_server_acted() {
+    assert_eq!(
+      send_failing(false, vec![limited(forgejo(500))]).await,
+      (false, 1)
+    );
+    assert_eq!(
+      send_failing(false, vec![limited(forgejo(429)), limited(forgejo(503))]).await,
+      (true, 3)
+    );
+  }
+
+  #[tokio::test]
+  async fn errors_that_are_not_transient_are_not_retried() {
+    assert_eq!(send_failing(true, vec![forgejo(404)]).await, (false, 1));
+  }
+
+  #[test]
+  fn encode_escapes_everything_but_unreserved_characters() {
+    assert_eq!(encode("group/project"), "group%2Fproject");
+    assert_eq!(encode("mc1.21-1.0.0+build_2~"), "mc1.21-1.0.0%2Bbuild_2~");
+  }
+}
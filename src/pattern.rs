@@ -0,0 +1,147 @@
+use crate::constants;
+
+/// Values substituted into artifact patterns; placeholders without a value match anything
+#[derive(Debug, Default)]
+pub struct Placeholders<'a> {
+  pub version: Option<&'a str>,
+  pub mc: Option<&'a str>,
+  pub loader: Option<&'a str>,
+  pub project: Option<&'a str>,
+}
+
+impl Placeholders<'_> {
+  /// Substitute `#`, `{version}`, `{mc}`, `{loader}` and `{project}` in the pattern, turning
+  /// placeholders without a value into `*` wildcards
+  pub fn expand(&self, pattern: &str) -> String {
+    let version = self.version.unwrap_or("*");
+
+    [
+      ("{mc}", self.mc),
+      ("{loader}", self.loader),
+      ("{project}", self.project),
+    ]
+    .into_iter()
+    .fold(
+      pattern
+        .replace(constants::VERSION_REPLACE, version)
+        .replace("{version}", version),
+      |pattern, (placeholder, value)| pattern.replace(placeholder, value.unwrap_or("*")),
+    )
+  }
+}
+
+/// Whether `name` matches the glob `pattern`, where `*` matches any run of characters and `?`
+/// matches any single character
+pub fn matches(pattern: &str, name: &str) -> bool {
+  let pattern = pattern.chars().collect::<Vec<_>>();
+  let name = name.chars().collect::<Vec<_>>();
+
+  let (mut p, mut n) = (0, 0);
+  let mut backtrack = None;
+
+  while n < name.len() {
+    match pattern.get(p) {
+      Some('*') => {
+        backtrack = Some((p, n));
+        p += 1;
+      }
+      Some(&c) if c == '?' || c == name[n] => {
+        p += 1;
+        n += 1;
+      }
+      _ => match backtrack {
+        Some((star, matched)) => {
+          p = star + 1;
+          n = matched + 1;
+          backtrack = Some((star, matched + 1));
+        }
+        None => return false,
+      },
+    }
+  }
+
+  pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Whether the pattern contains any wildcards
+pub fn is_glob(pattern: &str) -> bool {
+  pattern.contains(['*', '?'])
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn matches_literal_names() {
+    assert!(matches("mod-1.0.jar", "mod-1.0.jar"));
+    assert!(!matches("mod-1.0.jar", "mod-1.0.jar.sha1"));
+    assert!(!matches("mod-1.0.jar", "mod-1.0"));
+    assert!(matches("", ""));
+    assert!(!matches("", "mod.jar"));
+  }
+
+  #[test]
+  fn matches_stars() {
+    assert!(matches("*", ""));
+    assert!(matches("*", "mod-1.0.jar"));
+    assert!(matches("mod-*.jar", "mod-1.0.jar"));
+    assert!(matches("mod-*.jar", "mod-.jar"));
+    assert!(matches("*-sources.jar", "mod-1.0-sources.jar"));
+    assert!(matches("mod-**", "mod-1.0.jar"));
+    assert!(!matches("mod-*.jar", "mod-1.0.zip"));
+    assert!(!matches("*-sources.jar", "mod-1.0.jar"));
+  }
+
+  #[test]
+  fn matches_stars_by_backtracking() {
+    assert!(matches("*-1.0.jar", "mod-1.0-1.0.jar"));
+    assert!(matches("a*b*c", "aXbYbZc"));
+    assert!(!matches("a*b*c", "aXbYbZ"));
+  }
+
+  #[test]
+  fn matches_question_marks() {
+    assert!(matches("mod-?.0.jar", "mod-1.0.jar"));
+    assert!(!matches("mod-?.0.jar", "mod-10.0.jar"));
+    assert!(!matches("mod-?.0.jar", "mod-.0.jar"));
+    assert!(matches("m?d-*", "möd-1.0.jar"));
+  }
+
+  #[test]
+  fn is_glob_finds_wildcards() {
+    assert!(is_glob("mod-*.jar"));
+    assert!(is_glob("mod-?.jar"));
+    assert!(!is_glob("mod-1.0.jar"));
+  }
+
+  #[test]
+  fn expand_substitutes_placeholders() {
+    let placeholders = Placeholders {
+      version: Some("1.0"),
+      mc: Some("1.20.1"),
+      loader: Some("fabric"),
+      project: Some("mod"),
+    };
+
+    assert_eq!(
+      placeholders.expand("{project}-{loader}-{mc}-#.jar"),
+      "mod-fabric-1.20.1-1.0.jar"
+    );
+    assert_eq!(placeholders.expand("mod-{version}.jar"), "mod-1.0.jar");
+  }
+
+  #[test]
+  fn expand_turns_missing_placeholders_into_wildcards() {
+    let placeholders = Placeholders {
+      version: Some("1.0"),
+      ..Default::default()
+    };
+
+    assert_eq!(
+      placeholders.expand("{project}-{loader}-{mc}-#.jar"),
+      "*-*-*-1.0.jar"
+    );
+    assert_eq!(Placeholders::default().expand("mod-#.jar"), "mod-*.jar");
+  }
+}
@@ -0,0 +1,31 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::sync::LazyLock;
+
+/// Every progress bar is drawn through this, and the logger writes through it too so that log lines
+/// are printed above the bars instead of tearing them
+pub static PROGRESS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+/// A progress bar for uploading a file of the given size to a distributor
+pub fn upload(distributor: &str, name: &str, length: u64) -> ProgressBar {
+  let bar = PROGRESS.add(ProgressBar::new(length));
+
+  bar.set_style(
+    ProgressStyle::with_template(
+      "{prefix} [{bar:30}] {binary_bytes}/{binary_total_bytes} ({binary_bytes_per_sec}, {eta})",
+    )
+    .unwrap_or_else(|_| ProgressStyle::default_bar())
+    .progress_chars("=> "),
+  );
+  bar.set_prefix(format!("{} {}", distributor, name));
+
+  bar
+}
+
+/// Keep the bar on screen, marked as finished only if the upload succeeded
+pub fn finish<T, E>(bar: &ProgressBar, result: &Result<T, E>) {
+  if result.is_ok() {
+    bar.finish();
+  } else {
+    bar.abandon();
+  }
+}
@@ -0,0 +1,773 @@
+use crate::{
+  changelog,
+  configuration::{Artifact, Configuration, Git, ModrinthDependency},
+  constants,
+  distributor::{self, Context, Distributor},
+  error,
+  file::{self, ToRead},
+  git, hooks,
+  journal::{Journal, Shared, Step},
+  metadata::Metadata,
+  network,
+  pattern::Placeholders,
+  progress,
+  rollback::{self, Created},
+  template,
+  util::{self, get_keys, VersionType},
+  PublishArguments,
+};
+use futures_util::{future, TryStreamExt};
+use indicatif::ProgressBar;
+use inquire::{Confirm, Select, Text};
+use reqwest::{multipart::Part, Body};
+use serde_json::json;
+use std::{
+  env, fs,
+  path::{Path, PathBuf},
+};
+use tokio_util::codec::{BytesCodec, FramedRead};
+
+/// A file to be uploaded, along with the name it is uploaded under
+pub struct Asset {
+  pub name: String,
+  pub file: ToRead<PathBuf>,
+}
+
+impl Asset {
+  fn locate(
+    folder: &Path,
+    pattern: &str,
+    placeholders: &Placeholders,
+  ) -> Result<Self, error::AxolotlError> {
+    let file = ToRead::find(folder, &placeholders.expand(pattern))?;
+    let name = file
+      .path()
+      .file_name()
+      .map(|name| name.to_string_lossy().into_owned())
+      .unwrap_or_default();
+
+    Ok(Asset { name, file })
+  }
+
+  pub fn length(&self) -> Result<u64, error::AxolotlError> {
+    Ok(fs::metadata(self.file.path())?.len())
+  }
+
+  /// Stream the file from the start, advancing the progress bar as it is read
+  pub async fn stream(&self, progress: &ProgressBar) -> Result<Body, error::AxolotlError> {
+    let file = self.file.open().await?;
+    let progress = progress.clone();
+
+    progress.reset();
+
+    Ok(Body::wrap_stream(
+      FramedRead::new(file, BytesCodec::new())
+        .inspect_ok(move |chunk| progress.inc(chunk.len() as u64)),
+    ))
+  }
+
+  pub async fn part(&self, progress: &ProgressBar) -> Result<Part, error::AxolotlError> {
+    Ok(
+      Part::stream_with_length(self.stream(progress).await?, self.length()?)
+        .file_name(self.name.clone()),
+    )
+  }
+
+  /// A progress bar for uploading the file to the distributor
+  pub fn progress(&self, distributor: &str) -> Result<ProgressBar, error::AxolotlError> {
+    Ok(progress::upload(distributor, &self.name, self.length()?))
+  }
+}
+
+/// A located artifact along with everything resolved about it
+pub struct Target {
+  pub name: Option<String>,
+  pub artifact: Asset,
+  pub additional: Vec<Asset>,
+  pub sources: Option<Asset>,
+  pub game_versions: Vec<String>,
+  pub loaders: Vec<String>,
+  pub dependencies: Vec<ModrinthDependency>,
+}
+
+impl Target {
+  async fn resolve(
+    name: Option<&str>,
+    configuration: &Artifact,
+    dependencies: &[ModrinthDependency],
+    version: &str,
+  ) -> Result<Self, error::AxolotlError> {
+    let placeholders = placeholders(name, configuration, Some(version));
+    let artifact = Asset::locate(&configuration.folder, &configuration.pattern, &placeholders)?;
+    let additional = configuration
+      .additional
+      .iter()
+      .map(|pattern| Asset::locate(&configuration.folder, pattern, &placeholders))
+      .collect::<Result<Vec<_>, _>>()?;
+    let sources = configuration
+      .sources
+      .as_ref()
+      .map(|pattern| Asset::locate(&configuration.folder, pattern, &placeholders))
+      .transpose()?;
+
+    let (game_versions, loaders) = match (
+      configuration.game_versions.clone(),
+      configuration.loaders.clone(),
+    ) {
+      (Some(game_versions), Some(loaders)) => (game_versions, loaders),
+      (game_versions, loaders) => {
+        let metadata = Metadata::read(artifact.file.path())?;
+
+        let game_versions = match (game_versions, metadata.minecraft) {
+          (Some(game_versions), _) => game_versions,
+          (None, Some(requirement)) => requirement.game_versions().await?,
+          (None, None) => vec![],
+        };
+        let loaders = loaders.unwrap_or(metadata.loaders);
+
+        (game_versions, loaders)
+      }
+    };
+
+    if game_versions.is_empty() || loaders.is_empty() {
+      return Err(error::AxolotlError::Error(format!(
+        "Could not determine the {} of {} - set them in the configuration file",
+        if game_versions.is_empty() {
+          "game versions"
+        } else {
+          "loaders"
+        },
+        artifact.name
+      )));
+    }
+
+    Ok(Target {
+      name: name.map(str::to_string),
+      artifact,
+      additional,
+      sources,
+      game_versions,
+      loaders,
+      dependencies: dependencies.to_vec(),
+    })
+  }
+
+  /// The primary artifact followed by the additional ones
+  pub fn assets(&self) -> impl Iterator<Item = &Asset> {
+    std::iter::once(&self.artifact).chain(self.additional.iter())
+  }
+}
+
+/// Everything that has been resolved about the version being published, which every distributor
+/// publishes from
+pub struct Release {
+  pub version: String,
+  pub version_type: VersionType,
+  pub tag: String,
+  pub changelog: Option<String>,
+  pub targets: Vec<Target>,
+  /// The commit being released, if git integration is enabled
+  pub commit: Option<String>,
+}
+
+pub async fn publish(arguments: PublishArguments) -> Result<(), error::AxolotlError> {
+  let configuration = Configuration::read()?;
+  network::configure(configuration.network.as_ref());
+
+  let (keys, _) = get_keys()?;
+  let keys = keys.with_environment();
+
+  let commit = match &configuration.git {
+    Some(git) => Some(check_git(git)?),
+    None => None,
+  };
+
+  let mut distributors = distributor::configured(&configuration, &keys, arguments.dry_run);
+
+  for distributor in &mut distributors {
+    distributor.validate(&arguments)?;
+  }
+
+  let targets = configuration.targets();
+
+  if targets.is_empty() {
+    return Err(error::AxolotlError::Error(format!(
+      "No [artifact] or [targets] were found in {}",
+      constants::CONFIGURATION
+    )));
+  }
+
+  let resumed = match Journal::read()? {
+    Some(journal) if arguments.resume => {
+      if journal.commit.is_some() && commit.is_some() && journal.commit != commit {
+        return Err(error::AxolotlError::Error(format!(
+          "HEAD has moved since the publish of {} started - check out {} to resume it",
+          journal.tag,
+          journal.commit.unwrap_or_default()
+        )));
+      }
+
+      info!(
+        "Resuming the publish of {} started {}",
+        journal.tag, journal.started
+      );
+
+      Some(journal)
+    }
+    Some(journal) => {
+      warn!(
+        "Starting over - pass --resume to continue the unfinished publish of {} instead",
+        journal.tag
+      );
+
+      None
+    }
+    None if arguments.resume => {
+      return Err(error::AxolotlError::Error(
+        "There is no unfinished publish to resume".to_string(),
+      ))
+    }
+    None => None,
+  };
+
+  // the version, its type and the tag are settled first so that the pre-publish hooks get them
+  // too; anything they take from the artifact is read from the one built before the hooks run,
+  // and checked against the one the hooks build
+  let (version, version_type, tag, declared) = match &resumed {
+    Some(journal) => (
+      journal.version.clone(),
+      journal.version_type,
+      journal.tag.clone(),
+      None,
+    ),
+    None => {
+      let version_type = util::require(
+        arguments.version_type,
+        "version type",
+        "--version-type / AXOLOTL_VERSION_TYPE",
+        || {
+          Ok(
+            Select::new(
+              "Version type",
+              vec![VersionType::Release, VersionType::Beta, VersionType::Alpha],
+            )
+            .prompt()?,
+          )
+        },
+      )?;
+
+      let declared = match arguments.version {
+        Some(_) => None,
+        None => version_from_metadata(targets[0].0, targets[0].1)?,
+      };
+      if let (None, Some(declared)) = (&arguments.version, &declared) {
+        info!("Using version {} from the artifact's metadata", declared);
+      }
+
+      let version = util::require(
+        arguments.version.clone().or(declared.clone()),
+        "version",
+        "--version / AXOLOTL_VERSION",
+        || Ok(Text::new("Version").prompt()?),
+      )?;
+
+      let tag = match resolve(&targets, &version).await {
+        Ok(built) => tag(
+          &configuration,
+          &platforms(&built),
+          &version,
+          version_type,
+          commit.as_deref(),
+        )?,
+        Err(_) => tag(
+          &configuration,
+          &configured_platforms(&targets),
+          &version,
+          version_type,
+          commit.as_deref(),
+        )
+        .map_err(|error| match error {
+          error::AxolotlError::Error(message) => error::AxolotlError::Error(format!(
+            "{} - the artifact hasn't been built yet, so only the game versions and \
+             loaders set in {} are known",
+            message,
+            constants::CONFIGURATION
+          )),
+          error => error,
+        })?,
+      };
+
+      (version, version_type, tag, declared)
+    }
+  };
+
+  // the pre-publish hooks usually build the artifact, so they run before it's located
+  let hooks = configuration.hooks.as_ref();
+  let hooked = resumed
+    .as_ref()
+    .is_some_and(|journal| journal.completed(&Step::PrePublishHooks));
+
+  if hooked {
+    info!("The pre-publish hooks have already been run, skipping them");
+  } else {
+    // the artifact hasn't been located yet, so the hooks get the (possibly wildcarded) path it is
+    // expected at
+    let (name, artifact, _) = targets[0];
+    let expected = artifact
+      .folder
+      .join(placeholders(name, artifact, Some(&version)).expand(&artifact.pattern));
+
+    hooks::run(
+      "pre-publish",
+      hooks.map_or(&[], |hooks| &hooks.pre_publish),
+      &[
+        ("AXOLOTL_VERSION".to_string(), version.clone()),
+        ("AXOLOTL_TAG".to_string(), tag.clone()),
+        (
+          "AXOLOTL_VERSION_TYPE".to_string(),
+          version_type.to_string().to_lowercase(),
+        ),
+        (
+          "AXOLOTL_ARTIFACT".to_string(),
+          expected.to_string_lossy().into_owned(),
+        ),
+      ],
+      arguments.dry_run,
+    )?;
+  }
+
+  let resolved = resolve(&targets, &version).await?;
+
+  let mut journal = match resumed {
+    Some(journal) => journal,
+    None => {
+      // the hooks may have rebuilt the artifact from newer sources than the one read above
+      if declared.is_some() {
+        let built = version_from_metadata(targets[0].0, targets[0].1)?;
+
+        if built.is_some() && built != declared {
+          return Err(error::AxolotlError::Error(format!(
+            "The pre-publish hooks built version {} rather than {} - run again to publish it, \
+             or pass --version",
+            built.unwrap_or_default(),
+            version
+          )));
+        }
+      }
+
+      let built = self::tag(
+        &configuration,
+        &platforms(&resolved),
+        &version,
+        version_type,
+        commit.as_deref(),
+      )?;
+
+      if built != tag {
+        return Err(error::AxolotlError::Error(format!(
+          "The pre-publish hooks built the artifact for other game versions or loaders, tagged {} \
+           rather than {} - run again to publish it",
+          built, tag
+        )));
+      }
+
+      let changelog = changelog::read(
+        configuration
+          .changelog
+          .as_ref()
+          .map(|changelog| &changelog.source),
+        arguments.changelog_file,
+        &version,
+        &tag,
+      )?;
+
+      Journal::new(
+        version,
+        version_type,
+        tag,
+        changelog,
+        commit.clone(),
+        arguments.dry_run,
+      )
+    }
+  };
+
+  if !arguments.dry_run
+    && !arguments.yes
+    && !util::require(None, "confirmation", "--yes / AXOLOTL_YES", || {
+      Ok(
+        Confirm::new(&format!(
+          "{} {} ({})?",
+          if journal.steps.is_empty() {
+            "Publish"
+          } else {
+            "Resume publishing"
+          },
+          journal.tag,
+          journal.version_type
+        ))
+        .with_default(true)
+        .prompt()?,
+      )
+    })?
+  {
+    return Ok(());
+  }
+
+  journal.save()?;
+
+  let version = journal.version.clone();
+  let version_type = journal.version_type;
+  let tag = journal.tag.clone();
+
+  let mut environment = vec![
+    ("AXOLOTL_VERSION".to_string(), version.clone()),
+    ("AXOLOTL_TAG".to_string(), tag.clone()),
+    (
+      "AXOLOTL_VERSION_TYPE".to_string(),
+      version_type.to_string().to_lowercase(),
+    ),
+  ];
+
+  if !hooked {
+    journal.record(Step::PrePublishHooks)?;
+  }
+
+  let mut release = Release {
+    version,
+    version_type,
+    tag,
+    changelog: journal.changelog.clone(),
+    targets: resolved,
+    commit,
+  };
+
+  if let Some(template) = configuration
+    .changelog
+    .as_ref()
+    .and_then(|changelog| changelog.template.as_ref())
+  {
+    release.changelog = Some(changelog::render(template, &release)?);
+  }
+
+  if arguments.dry_run {
+    info!(
+      "Dry run: nothing will be published\n{}",
+      serde_json::to_string_pretty(&json!({
+        "version": release.version,
+        "version_type": release.version_type.to_string(),
+        "tag": release.tag,
+        "changelog": release.changelog,
+        "targets": release
+          .targets
+          .iter()
+          .map(|target| Ok(json!({
+            "name": target.name,
+            "artifacts": target
+              .assets()
+              .map(|asset| Ok(json!({
+                "path": asset.file.path(),
+                "size": fs::metadata(asset.file.path())?.len(),
+              })))
+              .collect::<Result<Vec<_>, error::AxolotlError>>()?,
+            "sources": target.sources.as_ref().map(|sources| sources.file.path()),
+            "game_versions": target.game_versions,
+            "loaders": target.loaders,
+          })))
+          .collect::<Result<Vec<_>, error::AxolotlError>>()?,
+      }))?
+    );
+  }
+
+  let distributed = distribute(
+    &configuration,
+    &mut distributors,
+    &release,
+    &mut journal,
+    &mut environment,
+    arguments.force,
+    arguments.dry_run,
+  )
+  .await;
+
+  if let Err(error) = distributed {
+    if arguments.atomic {
+      rollback::rollback(&distributors, &mut journal).await;
+    }
+
+    return Err(error);
+  }
+
+  // renamed only now, so that nothing is left behind if publishing is cancelled or fails
+  changelog::rename_unreleased(
+    configuration
+      .changelog
+      .as_ref()
+      .map(|changelog| &changelog.source),
+    journal.changelog.as_deref(),
+    &release.version,
+    arguments.dry_run,
+  )?;
+
+  environment.push((
+    "AXOLOTL_ARTIFACT".to_string(),
+    release.targets[0]
+      .artifact
+      .file
+      .path()
+      .to_string_lossy()
+      .into_owned(),
+  ));
+  environment.push((
+    "AXOLOTL_ARTIFACTS".to_string(),
+    env::join_paths(release.assets().map(|asset| asset.file.path()))
+      .map_err(|error| error::AxolotlError::Error(error.to_string()))?
+      .to_string_lossy()
+      .into_owned(),
+  ));
+
+  hooks::run(
+    "post-publish",
+    hooks.map_or(&[], |hooks| &hooks.post_publish),
+    &environment,
+    arguments.dry_run,
+  )?;
+
+  journal.finish()
+}
+
+/// Tag the release and upload it to every configured distributor, recording what was created in
+/// the journal so that it can be rolled back
+async fn distribute(
+  configuration: &Configuration,
+  distributors: &mut [Box<dyn Distributor + '_>],
+  release: &Release,
+  journal: &mut Journal,
+  environment: &mut Vec<(String, String)>,
+  force: bool,
+  dry_run: bool,
+) -> Result<(), error::AxolotlError> {
+  let journal = Shared::new(journal);
+  let context = Context {
+    release,
+    journal: &journal,
+    force,
+    dry_run,
+  };
+
+  future::try_join_all(
+    distributors
+      .iter_mut()
+      .map(|distributor| distributor.prepare(&context)),
+  )
+  .await?;
+
+  if let Some(git) = &configuration.git {
+    create_tag(git, release, &journal, dry_run)?;
+  }
+
+  // every distributor runs to the end even if another fails, so that everything they created is
+  // recorded
+  let published = future::join_all(
+    distributors
+      .iter()
+      .map(|distributor| distributor.publish(&context)),
+  )
+  .await;
+
+  let mut failed = None;
+
+  for (distributor, published) in distributors.iter().zip(published) {
+    match published {
+      Ok(urls) => environment.extend(distributor.environment(&urls)),
+      Err(error) if failed.is_none() => failed = Some(error),
+      Err(error) => error!(
+        "Distributing to {} failed too: {}",
+        distributor.registered().title,
+        error
+      ),
+    }
+  }
+
+  match failed {
+    Some(error) => Err(error),
+    None => Ok(()),
+  }
+}
+
+impl Release {
+  /// The assets of every target
+  pub fn assets(&self) -> impl Iterator<Item = &Asset> {
+    self.targets.iter().flat_map(Target::assets)
+  }
+}
+
+/// Check the working tree and return the commit being released
+fn check_git(configuration: &Git) -> Result<String, error::AxolotlError> {
+  if configuration.require_clean && !git::is_clean()? {
+    return Err(error::AxolotlError::Error(
+      "The working tree has uncommitted changes - commit or stash them before publishing"
+        .to_string(),
+    ));
+  }
+
+  git::head()
+}
+
+/// Make sure the tag exists locally (and optionally on the remote) and points at the released commit
+fn create_tag(
+  configuration: &Git,
+  release: &Release,
+  journal: &Shared<'_>,
+  dry_run: bool,
+) -> Result<(), error::AxolotlError> {
+  let commit = release.commit.clone().unwrap_or_default();
+  let head = git::head()?;
+
+  if head != commit {
+    return Err(error::AxolotlError::Error(format!(
+      "HEAD moved from {} to {} while publishing",
+      commit, head
+    )));
+  }
+
+  let mut created = false;
+
+  match git::tag_commit(&release.tag)? {
+    Some(tagged) if tagged != commit => {
+      return Err(error::AxolotlError::Error(format!(
+        "The tag {} already exists and points at {} rather than HEAD ({})",
+        release.tag, tagged, commit
+      )))
+    }
+    Some(_) => info!("The tag {} already points at HEAD", release.tag),
+    None if !configuration.tag => {}
+    None if dry_run => info!("Would create the tag {} at {}", release.tag, commit),
+    None => {
+      git::create_tag(
+        &release.tag,
+        release.changelog.as_deref().unwrap_or(&release.tag),
+      )?;
+
+      info!("Created the tag {} at {}", release.tag, commit);
+
+      journal.lock().created(Created::Tag {
+        name: release.tag.clone(),
+      });
+      created = true;
+    }
+  }
+
+  if configuration.push {
+    if dry_run {
+      info!(
+        "Would push the tag {} to {}",
+        release.tag, configuration.remote
+      );
+    } else {
+      git::push_tag(&configuration.remote, &release.tag)?;
+      info!("Pushed the tag {} to {}", release.tag, configuration.remote);
+
+      // a tag that already existed locally may have been on the remote too, so it is left alone
+      if created {
+        journal.lock().created(Created::PushedTag {
+          name: release.tag.clone(),
+          remote: configuration.remote.clone(),
+        });
+      }
+    }
+  }
+
+  Ok(())
+}
+
+/// Render the tag template (`v{{version}}` by default) with the variables known before the tag
+fn tag(
+  configuration: &Configuration,
+  (game_versions, loaders): &(Vec<String>, Vec<String>),
+  version: &str,
+  version_type: VersionType,
+  commit: Option<&str>,
+) -> Result<String, error::AxolotlError> {
+  template::render(
+    configuration.tag.as_deref().unwrap_or("v{{version}}"),
+    &changelog::variables(version, version_type, game_versions, loaders, commit),
+  )
+}
+
+/// Locate and read every target's artifacts
+async fn resolve(
+  targets: &[(Option<&str>, &Artifact, &[ModrinthDependency])],
+  version: &str,
+) -> Result<Vec<Target>, error::AxolotlError> {
+  let mut resolved = vec![];
+
+  for (name, artifact, dependencies) in targets {
+    resolved.push(Target::resolve(*name, artifact, dependencies, version).await?);
+  }
+
+  Ok(resolved)
+}
+
+/// The game versions and loaders of every target, without duplicates
+fn platforms(targets: &[Target]) -> (Vec<String>, Vec<String>) {
+  (
+    changelog::unique(targets.iter().flat_map(|target| &target.game_versions)),
+    changelog::unique(targets.iter().flat_map(|target| &target.loaders)),
+  )
+}
+
+/// The game versions and loaders set in the configuration file for every target, without
+/// duplicates
+fn configured_platforms(
+  targets: &[(Option<&str>, &Artifact, &[ModrinthDependency])],
+) -> (Vec<String>, Vec<String>) {
+  (
+    changelog::unique(
+      targets
+        .iter()
+        .flat_map(|(_, artifact, _)| artifact.game_versions.iter().flatten()),
+    ),
+    changelog::unique(
+      targets
+        .iter()
+        .flat_map(|(_, artifact, _)| artifact.loaders.iter().flatten()),
+    ),
+  )
+}
+
+/// The placeholder values known from the configuration alone
+fn placeholders<'a>(
+  name: Option<&'a str>,
+  configuration: &'a Artifact,
+  version: Option<&'a str>,
+) -> Placeholders<'a> {
+  let single = |values: &'a Option<Vec<String>>| match values.as_deref() {
+    Some([value]) => Some(value.as_str()),
+    _ => None,
+  };
+
+  Placeholders {
+    version,
+    mc: single(&configuration.game_versions),
+    loader: single(&configuration.loaders),
+    project: name,
+  }
+}
+
+/// Read the version from the metadata of the artifact, if the pattern matches exactly one file
+fn version_from_metadata(
+  name: Option<&str>,
+  configuration: &Artifact,
+) -> Result<Option<String>, error::AxolotlError> {
+  if !configuration.folder.is_dir() {
+    return Ok(None);
+  }
+
+  let pattern = placeholders(name, configuration, None).expand(&configuration.pattern);
+
+  match file::glob(&configuration.folder, &pattern)?.as_slice() {
+    [artifact] => Ok(Metadata::read(artifact)?.version),
+    _ => Ok(None),
+  }
+}
@@ -0,0 +1,103 @@
+use crate::{
+  configuration::Configuration, constants, error, modrinth, network, util::get_keys,
+  ReleaseArguments,
+};
+use modrinth_api::{
+  apis::versions_api,
+  models::{creatable_version, editable_version::Status, version, EditableVersion},
+};
+
+/// Move the Modrinth drafts of the project to the status they were requested to be released as
+pub async fn release(arguments: ReleaseArguments) -> Result<(), error::AxolotlError> {
+  let configuration = Configuration::read()?;
+  network::configure(configuration.network.as_ref());
+
+  let (keys, _) = get_keys()?;
+  let keys = keys.with_environment();
+
+  let Some(modrinth) = &configuration.modrinth else {
+    return Err(error::AxolotlError::Error(format!(
+      "No [modrinth] was found in {}",
+      constants::CONFIGURATION
+    )));
+  };
+
+  let Some(token) = keys.token(modrinth::REGISTERED.name) else {
+    return Err(error::AxolotlError::Error(
+      "A Modrinth token was not provided - run `axolotl keys set modrinth` or set AXOLOTL_MODRINTH_TOKEN"
+        .to_string(),
+    ));
+  };
+
+  let config = network::modrinth(Some(token))?;
+
+  let drafts = network::retry("Listing the versions", || async {
+    Ok(versions_api::get_project_versions(&config, &modrinth.id, None, None, None).await?)
+  })
+  .await?
+  .into_iter()
+  .filter(|version| version.status == Some(version::Status::Draft))
+  .filter(|version| {
+    arguments
+      .version
+      .as_ref()
+      .is_none_or(|number| &version.version_number == number)
+  })
+  .collect::<Vec<_>>();
+
+  if drafts.is_empty() {
+    warn!(
+      "No drafts{} were found",
+      match &arguments.version {
+        Some(version) => format!(" of {}", version),
+        None => String::new(),
+      }
+    );
+  }
+
+  for draft in drafts {
+    let status = match draft.requested_status.flatten() {
+      Some(version::RequestedStatus::Listed) => Status::Listed,
+      Some(version::RequestedStatus::Unlisted) => Status::Unlisted,
+      Some(version::RequestedStatus::Archived) => Status::Archived,
+      // drafts created without a requested status fall back to the configured one, and to listed
+      // if that is a draft too, which releasing wouldn't change
+      Some(version::RequestedStatus::Draft) | None => match modrinth.requested_status {
+        creatable_version::RequestedStatus::Unlisted => Status::Unlisted,
+        creatable_version::RequestedStatus::Archived => Status::Archived,
+        creatable_version::RequestedStatus::Listed | creatable_version::RequestedStatus::Draft => {
+          Status::Listed
+        }
+      },
+    };
+
+    let url = format!(
+      "https://modrinth.com/project/{}/version/{}",
+      draft.project_id, draft.id
+    );
+
+    if arguments.dry_run {
+      info!("Would release {} ({}) as {:?}", draft.name, url, status);
+      continue;
+    }
+
+    network::retry(&format!("Releasing {}", draft.name), || async {
+      Ok(
+        versions_api::modify_version(
+          &config,
+          &draft.id,
+          Some(EditableVersion {
+            status: Some(status),
+            ..EditableVersion::new()
+          }),
+        )
+        .await?,
+      )
+    })
+    .await?;
+
+    info!("Released {} ({}) as {:?}", draft.name, url, status);
+  }
+
+  Ok(())
+}
@@ -0,0 +1,286 @@
+use crate::{
+  distributor::{Distributor, Registered},
+  error, git,
+  journal::Journal,
+};
+use std::fmt;
+
+/// Something a publish created, which `--atomic` deletes again if a later step fails
+#[derive(Debug, Clone)]
+pub enum Created {
+  Tag {
+    name: String,
+  },
+  PushedTag {
+    name: String,
+    remote: String,
+  },
+  /// A release or version created by a distributor, along with its tag if the distributor created
+  /// it for the release
+  Distributed {
+    distributor: &'static Registered,
+    /// What the distributor calls it, such as a release or a version
+    kind: &'static str,
+    id: String,
+    url: String,
+    tag: Option<String>,
+  },
+}
+
+impl fmt::Display for Created {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Created::Tag { name } => write!(f, "the tag {}", name),
+      Created::PushedTag { name, remote } => write!(f, "the tag {} on {}", name, remote),
+      Created::Distributed {
+        distributor,
+        kind,
+        url,
+        tag,
+        ..
+      } => {
+        write!(f, "the {} {} at {}", distributor.title, kind, url)?;
+
+        if let Some(tag) = tag {
+          write!(f, " and its tag {}", tag)?;
+        }
+
+        Ok(())
+      }
+    }
+  }
+}
+
+/// Delete everything the publish created, newest first, and report what was undone
+///
+/// Failing to delete something is reported rather than returned, so that the error which caused
+/// the rollback is the one the publish fails with
+pub async fn rollback(distributors: &[Box<dyn Distributor + '_>], journal: &mut Journal) {
+  let created = journal.take_created();
+
+  if created.is_empty() {
+    info!("Nothing was created, so there is nothing to roll back");
+    return;
+  }
+
+  warn!("Rolling back the publish");
+
+  let mut undone = vec![];
+  let mut failed = vec![];
+
+  for created in created.into_iter().rev() {
+    match undo(distributors, &created).await {
+      Ok(()) => {
+        journal.forget(&created);
+        undone.push(created);
+      }
+      Err(error) => {
+        error!("Could not delete {}: {}", created, error);
+        failed.push(created);
+      }
+    }
+  }
+
+  if let Err(error) = journal.save() {
+    error!("Could not save the publish state: {}", error);
+  }
+
+  if !undone.is_empty() {
+    info!(
+      "Rolled back:\n{}",
+      undone
+        .iter()
+        .map(|created| format!("  - {}", created))
+        .collect::<Vec<_>>()
+        .join("\n")
+    );
+  }
+
+  if !failed.is_empty() {
+    error!(
+      "Could not roll back (these have to be deleted by hand):\n{}",
+      failed
+        .iter()
+        .map(|created| format!("  - {}", created))
+        .collect::<Vec<_>>()
+        .join("\n")
+    );
+  }
+}
+
+async fn undo(
+  distributors: &[Box<dyn Distributor + '_>],
+  created: &Created,
+) -> Result<(), error::AxolotlError> {
+  match created {
+    Created::Tag { name } => git::delete_tag(name),
+    Created::PushedTag { name, remote } => git::delete_remote_tag(remote, name),
+    Created::Distributed {
+      distributor,
+      kind,
+      id,
+      tag,
+      ..
+    } => {
+      let Some(distributor) = distributors
+        .iter()
+        .find(|candidate| candidate.registered().name == distributor.name)
+      else {
+        return Err(error::AxolotlError::Error(format!(
+          "{} is not configured",
+          distributor.title
+        )));
+      };
+
+      distributor.rollback(kind, id, tag.as_deref()).await
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    configuration::Configuration, distributor::Context, journal::Step, keys::Keys,
+    util::VersionType,
+  };
+  use async_trait::async_trait;
+  use std::sync::Mutex;
+
+  static FAKE: Registered = Registered {
+    name: "fake",
+    alias: "fk",
+    title: "Fake",
+    create,
+  };
+
+  fn create<'a>(_: &'a Configuration, _: &Keys, _: bool) -> Option<Box<dyn Distributor + 'a>> {
+    None
+  }
+
+  /// A distributor that records what it is asked to roll back, failing for one id
+  struct Fake<'a> {
+    rolled_back: &'a Mutex<Vec<String>>,
+    failing: &'a str,
+  }
+
+  #[async_trait]
+  impl Distributor for Fake<'_> {
+    fn registered(&self) -> &'static Registered {
+      &FAKE
+    }
+
+    async fn publish(
+      &self,
+      _context: &Context<'_, '_>,
+    ) -> Result<Vec<String>, error::AxolotlError> {
+      Ok(vec![])
+    }
+
+    async fn rollback(
+      &self,
+      kind: &str,
+      id: &str,
+      tag: Option<&str>,
+    ) -> Result<(), error::AxolotlError> {
+      self
+        .rolled_back
+        .lock()
+        .unwrap()
+        .push(format!("{} {} {:?}", kind, id, tag));
+
+      if id == self.failing {
+        return Err(error::AxolotlError::Error("refused".to_string()));
+      }
+
+      Ok(())
+    }
+  }
+
+  fn release(id: &str, tag: Option<&str>) -> Created {
+    Created::Distributed {
+      distributor: &FAKE,
+      kind: "release",
+      id: id.to_string(),
+      url: format!("https://example.com/{}", id),
+      tag: tag.map(str::to_string),
+    }
+  }
+
+  fn published(id: &str) -> Step {
+    Step::Published {
+      distributor: FAKE.name.to_string(),
+      name: id.to_string(),
+      id: id.to_string(),
+      url: format!("https://example.com/{}", id),
+    }
+  }
+
+  /// A journal for a dry run, which is never saved, with a published step for each created release
+  fn journal(ids: &[&str]) -> Journal {
+    let mut journal = Journal::new(
+      "1.0.0".to_string(),
+      VersionType::Release,
+      "v1.0.0".to_string(),
+      None,
+      None,
+      true,
+    );
+
+    for id in ids {
+      journal.record(published(id)).unwrap();
+      journal.created(release(id, (*id == "1").then_some("v1.0.0")));
+    }
+
+    journal
+  }
+
+  #[tokio::test]
+  async fn rollback_undoes_the_newest_first_and_forgets_what_was_undone() {
+    let rolled_back = Mutex::new(vec![]);
+    let distributors: Vec<Box<dyn Distributor>> = vec![Box::new(Fake {
+      rolled_back: &rolled_back,
+      failing: "2",
+    })];
+    let mut journal = journal(&["1", "2", "3"]);
+
+    rollback(&distributors, &mut journal).await;
+
+    assert_eq!(
+      *rolled_back.lock().unwrap(),
+      [
+        "release 3 None",
+        "release 2 None",
+        "release 1 Some(\"v1.0.0\")"
+      ]
+    );
+    // the release that couldn't be deleted is still published, so resuming reuses it
+    assert_eq!(journal.steps, [published("2")]);
+    assert!(journal.take_created().is_empty());
+  }
+
+  #[tokio::test]
+  async fn rollback_fails_for_distributors_that_are_not_configured() {
+    let mut journal = journal(&["1"]);
+
+    rollback(&[], &mut journal).await;
+
+    assert_eq!(journal.steps, [published("1")]);
+  }
+
+  #[test]
+  fn created_names_what_was_created() {
+    assert_eq!(
+      release("1", Some("v1.0.0")).to_string(),
+      "the Fake release at https://example.com/1 and its tag v1.0.0"
+    );
+    assert_eq!(
+      Created::PushedTag {
+        name: "v1.0.0".to_string(),
+        remote: "origin".to_string()
+      }
+      .to_string(),
+      "the tag v1.0.0 on origin"
+    );
+  }
+}
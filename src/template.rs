@@ -0,0 +1,184 @@
+use crate::error;
+use std::collections::HashMap;
+
+/// Render a template with `{{variable}}` placeholders and `{{#if variable}}`/`{{#unless variable}}`
+/// blocks (with an optional `{{else}}`), where a variable is truthy if it is not empty
+pub fn render(
+  template: &str,
+  variables: &HashMap<&str, String>,
+) -> Result<String, error::AxolotlError> {
+  // whether each enclosing block is currently rendered, along with its name
+  let mut blocks: Vec<(&str, bool)> = vec![];
+  let mut output = String::new();
+  let mut rest = template;
+
+  let lookup = |name: &str| {
+    variables.get(name).ok_or_else(|| {
+      error::AxolotlError::Error(format!("Unknown template variable {{{{{}}}}}", name))
+    })
+  };
+
+  while let Some(start) = rest.find("{{") {
+    let active = blocks.iter().all(|(_, active)| *active);
+
+    if active {
+      output.push_str(&rest[..start]);
+    }
+
+    let end = rest[start..]
+      .find("}}")
+      .ok_or_else(|| error::AxolotlError::Error("Unclosed {{ in template".to_string()))?
+      + start;
+    let tag = rest[start + 2..end].trim();
+    rest = &rest[end + 2..];
+
+    if let Some(name) = tag.strip_prefix("#if ") {
+      blocks.push(("if", !lookup(name.trim())?.is_empty()));
+    } else if let Some(name) = tag.strip_prefix("#unless ") {
+      blocks.push(("unless", lookup(name.trim())?.is_empty()));
+    } else if tag == "else" {
+      match blocks.last_mut() {
+        Some((_, active)) => *active = !*active,
+        None => {
+          return Err(error::AxolotlError::Error(
+            "{{else}} outside of a block in template".to_string(),
+          ))
+        }
+      }
+    } else if let Some(kind) = tag.strip_prefix('/') {
+      match blocks.pop() {
+        Some((open, _)) if open == kind.trim() => {}
+        _ => {
+          return Err(error::AxolotlError::Error(format!(
+            "Unexpected {{{{{}}}}} in template",
+            tag
+          )))
+        }
+      }
+    } else if active {
+      output.push_str(lookup(tag)?);
+    }
+  }
+
+  if let Some((kind, _)) = blocks.last() {
+    return Err(error::AxolotlError::Error(format!(
+      "Unclosed {{{{#{}}}}} in template",
+      kind
+    )));
+  }
+
+  output.push_str(rest);
+
+  Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn variables() -> HashMap<&'static str, String> {
+    HashMap::from([
+      ("version", "1.0.0".to_string()),
+      ("mc", "1.20.1".to_string()),
+      ("loader", String::new()),
+    ])
+  }
+
+  fn error(template: &str) -> String {
+    match render(template, &variables()) {
+      Err(error::AxolotlError::Error(message)) => message,
+      result => panic!(
+        "expected an error, got {:?}",
+        result.map_err(|e| e.to_string())
+      ),
+    }
+  }
+
+  #[test]
+  fn render_substitutes_variables() {
+    assert_eq!(
+      render("v{{version}} for {{ mc }}", &variables()).unwrap(),
+      "v1.0.0 for 1.20.1"
+    );
+    assert_eq!(
+      render("no variables", &variables()).unwrap(),
+      "no variables"
+    );
+  }
+
+  #[test]
+  fn render_if_blocks() {
+    assert_eq!(
+      render("a{{#if mc}} {{mc}}{{/if}}", &variables()).unwrap(),
+      "a 1.20.1"
+    );
+    assert_eq!(
+      render("a{{#if loader}} {{loader}}{{/if}}", &variables()).unwrap(),
+      "a"
+    );
+  }
+
+  #[test]
+  fn render_else_blocks() {
+    assert_eq!(
+      render("{{#if loader}}{{loader}}{{else}}any{{/if}}", &variables()).unwrap(),
+      "any"
+    );
+    assert_eq!(
+      render("{{#if mc}}{{mc}}{{else}}any{{/if}}", &variables()).unwrap(),
+      "1.20.1"
+    );
+  }
+
+  #[test]
+  fn render_unless_blocks() {
+    assert_eq!(
+      render(
+        "{{#unless loader}}any{{else}}{{loader}}{{/unless}}",
+        &variables()
+      )
+      .unwrap(),
+      "any"
+    );
+    assert_eq!(
+      render("{{#unless mc}}any{{/unless}}", &variables()).unwrap(),
+      ""
+    );
+  }
+
+  #[test]
+  fn render_nested_blocks() {
+    let template = "{{#if mc}}{{#if loader}}{{loader}}{{else}}vanilla{{/if}} {{mc}}{{/if}}";
+
+    assert_eq!(render(template, &variables()).unwrap(), "vanilla 1.20.1");
+    assert_eq!(
+      render(
+        "{{#if loader}}{{#if mc}}{{mc}}{{/if}}{{else}}none{{/if}}",
+        &variables()
+      )
+      .unwrap(),
+      "none"
+    );
+  }
+
+  #[test]
+  fn render_rejects_unknown_variables() {
+    assert_eq!(error("{{name}}"), "Unknown template variable {{name}}");
+    assert_eq!(
+      error("{{#if name}}{{/if}}"),
+      "Unknown template variable {{name}}"
+    );
+  }
+
+  #[test]
+  fn render_rejects_unbalanced_tags() {
+    assert_eq!(error("{{version"), "Unclosed {{ in template");
+    assert_eq!(error("{{else}}"), "{{else}} outside of a block in template");
+    assert_eq!(error("{{/if}}"), "Unexpected {{/if}} in template");
+    assert_eq!(
+      error("{{#if mc}}{{/unless}}"),
+      "Unexpected {{/unless}} in template"
+    );
+    assert_eq!(error("{{#if mc}}"), "Unclosed {{#if}} in template");
+  }
+}
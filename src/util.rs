@@ -1,21 +1,39 @@
 use crate::{error, keys::Keys};
+use clap::ValueEnum;
 use inquire::{Password, PasswordDisplayMode};
 use modrinth_api::models;
-use std::fmt::Display;
+use serde::{Deserialize, Serialize};
+use std::{
+  env,
+  fmt::Display,
+  io::{self, IsTerminal},
+};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[clap(rename_all = "lower")]
+#[serde(rename_all = "lowercase")]
 pub enum VersionType {
   Release,
   Beta,
   Alpha,
 }
 
-impl Into<models::creatable_version::VersionType> for VersionType {
-  fn into(self) -> models::creatable_version::VersionType {
-    match self {
-      Self::Release => models::creatable_version::VersionType::Release,
-      Self::Beta => models::creatable_version::VersionType::Beta,
-      Self::Alpha => models::creatable_version::VersionType::Alpha,
+impl From<VersionType> for models::creatable_version::VersionType {
+  fn from(value: VersionType) -> Self {
+    match value {
+      VersionType::Release => Self::Release,
+      VersionType::Beta => Self::Beta,
+      VersionType::Alpha => Self::Alpha,
+    }
+  }
+}
+
+impl From<VersionType> for models::editable_version::VersionType {
+  fn from(value: VersionType) -> Self {
+    match value {
+      VersionType::Release => Self::Release,
+      VersionType::Beta => Self::Beta,
+      VersionType::Alpha => Self::Alpha,
     }
   }
 }
@@ -34,19 +52,50 @@ impl Display for VersionType {
   }
 }
 
+/// Whether prompts can be shown, i.e. whether stdin is attached to a terminal
+pub fn interactive() -> bool {
+  io::stdin().is_terminal()
+}
+
+/// Use the given value if present, otherwise prompt for it; fails instead of prompting when
+/// stdin is not a terminal so that headless runs don't hang
+pub fn require<T>(
+  value: Option<T>,
+  description: &str,
+  source: &str,
+  prompt: impl FnOnce() -> Result<T, error::AxolotlError>,
+) -> Result<T, error::AxolotlError> {
+  match value {
+    Some(value) => Ok(value),
+    None if interactive() => prompt(),
+    None => Err(error::AxolotlError::Error(format!(
+      "No {} was provided ({}) and stdin is not a terminal",
+      description, source
+    ))),
+  }
+}
+
 pub fn read_key() -> Result<String, error::AxolotlError> {
   read_key_confirmation(false)
 }
 
 pub fn read_key_confirmation(confirm: bool) -> Result<String, error::AxolotlError> {
-  let mut password =
-    Password::new("Enter your passphrase").with_display_mode(PasswordDisplayMode::Masked);
-
   if !confirm {
-    password.enable_confirmation = confirm;
+    if let Ok(key) = env::var("AXOLOTL_PASSPHRASE") {
+      return Ok(key);
+    }
   }
 
-  Ok(password.prompt()?)
+  require(None, "passphrase", "AXOLOTL_PASSPHRASE", || {
+    let mut password =
+      Password::new("Enter your passphrase").with_display_mode(PasswordDisplayMode::Masked);
+
+    if !confirm {
+      password.enable_confirmation = confirm;
+    }
+
+    Ok(password.prompt()?)
+  })
 }
 
 pub fn get_keys() -> Result<(Keys, Option<String>), error::AxolotlError> {
@@ -60,5 +109,5 @@ pub fn get_keys() -> Result<(Keys, Option<String>), error::AxolotlError> {
     return Ok((keys, Some(key)));
   }
 
-  return Ok((keys, None));
+  Ok((keys, None))
 }